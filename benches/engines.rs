@@ -0,0 +1,29 @@
+use std::time::Instant;
+
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::{Budget, Engine, Outcome, SynacorVm};
+
+// the confirmation routine, called as the teleporter does
+const ENTRY: usize = 6027;
+const WARM_UP: u64 = 1_000_000;
+const STEPS: u64 = 300_000_000;
+
+// Runs the teleporter's confirmation routine, which goes on for far longer than
+// this, with each engine and prints how many instructions per second they get
+// through once warmed up.
+fn main() {
+    let program = read_input_u16("input/challenge.bin");
+    for &engine in [Engine::Interpreter, Engine::Blocks].iter() {
+        let mut vm = SynacorVm::new(program.clone());
+        vm.set_engine(engine);
+        vm.set_register(0, 4);
+        vm.set_register(1, 1);
+        vm.set_register(7, 1);
+        vm.jump(ENTRY);
+        assert_eq!(vm.run_budgeted(&Budget::instructions(WARM_UP)), Outcome::BudgetExhausted);
+        let start = Instant::now();
+        assert_eq!(vm.run_budgeted(&Budget::instructions(STEPS)), Outcome::BudgetExhausted);
+        let elapsed = start.elapsed().as_secs_f64();
+        println!("{:?}: {} instructions in {:.3}s, {:.1}M/s", engine, STEPS, elapsed, STEPS as f64 / elapsed / 1e6);
+    }
+}
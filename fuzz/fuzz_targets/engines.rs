@@ -0,0 +1,20 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use synacor_challenge::bytes_to_u16;
+use synacor_challenge::synacor_vm::{Budget, Engine, SynacorVm};
+
+// Every engine stops any program the same way, in the same state.
+fuzz_target!(|data: &[u8]| {
+    let program = bytes_to_u16(data);
+    let run = |engine| {
+        let mut vm = SynacorVm::new(program.clone());
+        vm.set_engine(engine);
+        vm.feed("look\n");
+        let outcome = vm.run_budgeted(&Budget::instructions(100_000));
+        let registers: Vec<u16> = (0..8).map(|r| vm.register(r)).collect();
+        (outcome, vm.ip(), vm.steps(), registers, vm.stack().to_vec(), vm.take_output(), vm.memory().to_vec())
+    };
+    let expected = run(Engine::Interpreter);
+    assert!(run(Engine::Blocks) == expected);
+});
@@ -0,0 +1,23 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use synacor_challenge::bytes_to_u16;
+use synacor_challenge::synacor_vm::{Budget, Fault, Outcome, SynacorVm};
+
+// Any bytes make a program. Running it must never panic, only stop with an
+// outcome, and leave registers, stack and ip valid whatever it did.
+fuzz_target!(|data: &[u8]| {
+    let mut vm = SynacorVm::new(bytes_to_u16(data));
+    vm.feed("look\n");
+    let outcome = vm.run_budgeted(&Budget::instructions(100_000));
+    assert!((0..8).all(|r| vm.register(r) <= 32767));
+    assert!(vm.stack().iter().all(|&x| x <= 32767));
+    match outcome {
+        Outcome::OutOfMemory => assert!(vm.ip() >= vm.memory().len()),
+        // ip is only checked before executing the next instruction
+        Outcome::BudgetExhausted => {}
+        // stopped on the word that is no opcode
+        Outcome::Fault(Fault::UnknownOpcode(opcode)) => assert!(opcode > 21 && vm.memory().get(vm.ip()) == Some(&opcode)),
+        _ => assert!(vm.ip() < vm.memory().len())
+    }
+});
@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::disasm::{self, Instruction, CALL, HALT, IN, JF, JMP, JT, OUT, POP, PUSH, RET, RMEM, WMEM};
+
+pub struct RegisterUsage {
+    pub register: usize,
+    pub reads: Vec<Instruction>,
+    pub writes: Vec<Instruction>
+}
+
+/// For each register, the instructions reading or writing it, see disasm::all_code.
+pub fn register_usage(memory: &[u16]) -> Vec<RegisterUsage> {
+    let code = disasm::all_code(memory);
+    (0..8).map(|register| {
+        let reads = code.values().filter(|i| disasm::reads(i, register)).cloned().collect();
+        let writes = code.values().filter(|i| disasm::writes(i, register)).cloned().collect();
+        RegisterUsage { register, reads, writes }
+    }).collect()
+}
+
+/// A subroutine whose effect is a function of a few registers: it only touches
+/// registers and its own stack, which it leaves as it found it, and only calls
+/// itself or other pure subroutines.
+/// Calling it again with the same `inputs` leaves the same values in `outputs`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PureFunction {
+    pub entry: usize,
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<usize>
+}
+
+/// Checks the subroutine at `entry`, given the subroutines already known to be pure.
+pub fn pure_function(memory: &[u16], entry: usize, known: &HashMap<usize, PureFunction>) -> Option<PureFunction> {
+    let body = disasm::function_body(memory, entry);
+    let mut inputs = BTreeSet::new();
+    let mut outputs = BTreeSet::new();
+    for instruction in body.values() {
+        match instruction.opcode {
+            // memory, I/O, stopping
+            HALT | RMEM | WMEM | OUT | IN => return None,
+            // jumps to a register go who knows where
+            JMP if disasm::literal(instruction.args[0]).is_none() => return None,
+            JT | JF if disasm::literal(instruction.args[1]).is_none() => return None,
+            CALL => {
+                let target = disasm::literal(instruction.args[0])? as usize;
+                if target != entry {
+                    let callee = known.get(&target)?;
+                    inputs.extend(callee.inputs.iter().cloned());
+                    outputs.extend(callee.outputs.iter().cloned());
+                }
+            }
+            _ => {}
+        }
+        inputs.extend((0..8).filter(|&r| disasm::reads(instruction, r)));
+        outputs.extend((0..8).filter(|&r| disasm::writes(instruction, r)));
+    }
+    if body.is_empty() || !balanced(&body, entry) { return None; }
+    Some(PureFunction {
+        entry,
+        inputs: inputs.into_iter().collect(),
+        outputs: outputs.into_iter().collect()
+    })
+}
+
+// Whether the stack is as deep at every `ret` as on entry, without ever popping
+// the caller's words. Calls are taken to leave the stack as they found it, which
+// holds for the pure subroutines and for the one checked. An instruction reached
+// with two different depths is rejected too.
+fn balanced(body: &BTreeMap<usize, Instruction>, entry: usize) -> bool {
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut todo = vec![(entry, 0)];
+    while let Some((address, depth)) = todo.pop() {
+        if let Some(&seen) = depths.get(&address) {
+            if seen != depth { return false; }
+            continue;
+        }
+        depths.insert(address, depth);
+        let instruction = match body.get(&address) {
+            Some(instruction) => instruction,
+            None => return false
+        };
+        let depth = match instruction.opcode {
+            PUSH => depth + 1,
+            POP if depth == 0 => return false,
+            POP => depth - 1,
+            RET if depth != 0 => return false,
+            _ => depth
+        };
+        todo.extend(instruction.successors().into_iter().map(|next| (next, depth)));
+    }
+    true
+}
+
+/// Every pure subroutine called from somewhere in memory.
+pub fn pure_functions(memory: &[u16]) -> HashMap<usize, PureFunction> {
+    let entries: BTreeSet<usize> = disasm::code(memory).values()
+        .filter(|instruction| instruction.opcode == CALL)
+        .filter_map(|instruction| disasm::literal(instruction.args[0]))
+        .map(|a| a as usize)
+        .collect();
+    let mut pure = HashMap::new();
+    // callees have to be found pure before their callers
+    loop {
+        let found: Vec<_> = entries.iter()
+            .filter(|entry| !pure.contains_key(*entry))
+            .filter_map(|&entry| pure_function(memory, entry, &pure))
+            .collect();
+        if found.is_empty() { break pure; }
+        for function in found {
+            pure.insert(function.entry, function);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+// Letters that look like another letter when seen in a mirror. Letters not listed
+// either read the same (A, H, I, M, O, T, U, V, W, X, Y, i, l, o, u, v, w, x...)
+// or have no mirror counterpart and are kept as they are.
+const MIRROR_PAIRS: [(char, char); 2] = [('b', 'd'), ('p', 'q')];
+
+fn mirror_char(c: char) -> char {
+    for &(x, y) in MIRROR_PAIRS.iter() {
+        if c == x { return y; }
+        if c == y { return x; }
+    }
+    c
+}
+
+/// Reads a code the way it appears in a mirror: reversed, with b/d and p/q swapped.
+/// qAddloUWuIAd => bAIuWUolbbAp
+pub fn mirror(code: &str) -> String {
+    code.chars().rev().map(mirror_char).collect()
+}
+
+/// Codes are 12 letters long, mixing upper and lower case (oHVlEiuRDDqk).
+fn is_code(word: &str) -> bool {
+    word.len() == 12
+        && word.chars().all(|c| c.is_ascii_alphabetic())
+        && word.chars().skip(1).any(|c| c.is_ascii_uppercase())
+        && word.chars().any(|c| c.is_ascii_lowercase())
+}
+
+pub fn find_codes(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| is_code(word))
+        .map(|word| word.to_string())
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Code {
+    pub text: String,
+    // seen through the mirror, so what has to be submitted is the mirrored text
+    pub mirrored: bool,
+}
+
+impl Code {
+    pub fn mirrored_text(&self) -> String {
+        mirror(&self.text)
+    }
+
+    pub fn submission(&self) -> String {
+        if self.mirrored { self.mirrored_text() } else { self.text.clone() }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.mirrored {
+            write!(f, "{} - seen in the mirror, submit {}", self.text, self.submission())
+        } else {
+            write!(f, "{} - mirrored: {}", self.text, self.mirrored_text())
+        }
+    }
+}
+
+/// Collects the codes showing up in the VM output, remembering the command
+/// that produced each piece of output.
+#[derive(Clone, Debug, Default)]
+pub struct CodeDetector {
+    codes: Vec<Code>,
+    last_command: String,
+}
+
+impl CodeDetector {
+    pub fn new() -> CodeDetector {
+        CodeDetector::default()
+    }
+
+    pub fn command(&mut self, line: &str) {
+        self.last_command = line.trim().to_string();
+    }
+
+    pub fn scan(&mut self, output: &str) -> Vec<Code> {
+        let mirrored = self.last_command == "use mirror";
+        let found: Vec<_> = find_codes(output).into_iter()
+            .filter(|text| !self.codes.iter().any(|code| code.text == *text))
+            .map(|text| Code { text, mirrored })
+            .collect();
+        self.codes.extend(found.iter().cloned());
+        found
+    }
+
+    pub fn codes(&self) -> &[Code] {
+        &self.codes
+    }
+}
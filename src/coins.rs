@@ -0,0 +1,152 @@
+use crate::game;
+use crate::synacor_vm::SynacorVm;
+
+/// One signed product of slots, e.g. `- _` or `_ * _^2`.
+/// Slots are (index of the slot in the equation, exponent).
+#[derive(Clone, Debug, PartialEq)]
+struct Term {
+    sign: i64,
+    slots: Vec<(usize, u32)>,
+}
+
+/// The monument equation: slots combined with `+`, `-`, `*` and `^`, equal to a target.
+/// _ + _ * _^2 + _^3 - _ = 399
+#[derive(Clone, Debug, PartialEq)]
+pub struct Equation {
+    terms: Vec<Term>,
+    slot_count: usize,
+    target: i64,
+}
+
+impl Equation {
+    pub fn parse(text: &str) -> Option<Equation> {
+        let mut sides = text.split('=');
+        let (left, right) = (sides.next()?, sides.next()?);
+        if sides.next().is_some() { return None; }
+        let target = right.trim().parse().ok()?;
+
+        let tokens: Vec<char> = left.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut terms = Vec::new();
+        let mut slot_count = 0;
+        let mut i = 0;
+        let mut sign = 1;
+        if tokens.first() == Some(&'-') {
+            sign = -1;
+            i += 1;
+        }
+        loop {
+            let mut slots = Vec::new();
+            loop {
+                if tokens.get(i) != Some(&'_') { return None; }
+                i += 1;
+                let mut exp = 1;
+                if tokens.get(i) == Some(&'^') {
+                    let digits: String = tokens[i + 1..].iter()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    exp = digits.parse().ok()?;
+                    i += 1 + digits.len();
+                }
+                slots.push((slot_count, exp));
+                slot_count += 1;
+                if tokens.get(i) == Some(&'*') { i += 1; } else { break; }
+            }
+            terms.push(Term { sign, slots });
+            match tokens.get(i) {
+                Some('+') => sign = 1,
+                Some('-') => sign = -1,
+                None => break,
+                _ => return None
+            }
+            i += 1;
+        }
+        Some(Equation { terms, slot_count, target })
+    }
+
+    /// Finds the line of the monument holding the equation.
+    pub fn find(text: &str) -> Option<Equation> {
+        text.lines()
+            .filter(|line| line.contains('_') && line.contains('='))
+            .find_map(Equation::parse)
+    }
+
+    // An overflow anywhere, as a large exponent gives, means it does not hold.
+    fn holds(&self, values: &[i64]) -> bool {
+        let sum = self.terms.iter().try_fold(0i64, |sum, term| {
+            let product = term.slots.iter()
+                .try_fold(1i64, |product, &(slot, exp)| product.checked_mul(values[slot].checked_pow(exp)?))?;
+            sum.checked_add(term.sign * product)
+        });
+        sum == Some(self.target)
+    }
+}
+
+const NUMBERS: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+const SHAPES: [(&str, i64); 6] = [
+    ("triangle", 3), ("square", 4), ("pentagon", 5), ("hexagon", 6), ("heptagon", 7), ("octagon", 8)
+];
+
+/// Reads the value of a coin from its `look` description:
+/// "It has nine dots on one side." or "It has a pentagon on one side."
+pub fn coin_value(description: &str) -> Option<i64> {
+    let words: Vec<&str> = description
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if let Some(i) = words.iter().position(|&word| word == "dots" || word == "dot") {
+        let count = words.get(i.checked_sub(1)?)?;
+        return count.parse().ok()
+            .or_else(|| NUMBERS.iter().position(|n| n == count).map(|n| n as i64));
+    }
+    SHAPES.iter()
+        .find(|(shape, _)| words.contains(shape))
+        .map(|&(_, value)| value)
+}
+
+/// The coins among the items of the inventory.
+pub fn inventory_coins(inventory: &[String]) -> Vec<String> {
+    inventory.iter()
+        .filter(|item| item.ends_with(" coin"))
+        .cloned()
+        .collect()
+}
+
+fn permute(coins: &mut Vec<(String, i64)>, k: usize, equation: &Equation) -> bool {
+    if k == coins.len() {
+        let values: Vec<_> = coins.iter().map(|&(_, value)| value).collect();
+        return equation.holds(&values);
+    }
+    for i in k..coins.len() {
+        coins.swap(k, i);
+        if permute(coins, k + 1, equation) { return true; }
+        coins.swap(k, i);
+    }
+    false
+}
+
+/// Orders the coins so that their values, put into the slots from left to right,
+/// satisfy the equation.
+pub fn solve(equation: &Equation, coins: &[(String, i64)]) -> Option<Vec<String>> {
+    if coins.len() != equation.slot_count { return None; }
+    let mut coins = coins.to_vec();
+    if permute(&mut coins, 0, equation) {
+        Some(coins.into_iter().map(|(name, _)| name).collect())
+    } else {
+        None
+    }
+}
+
+/// Given a VM waiting for a command at the monument with all the coins in the
+/// inventory, works out the `use ... coin` commands that open the north door.
+/// The VM itself is left untouched.
+pub fn coin_commands(vm: &SynacorVm) -> Option<String> {
+    let mut vm = vm.clone();
+    let equation = Equation::find(&game::play(&mut vm, "look").ok()?.room?.description)?;
+    let mut coins = Vec::new();
+    for coin in inventory_coins(&game::play(&mut vm, "inv").ok()?.inventory?) {
+        let value = coin_value(&game::play(&mut vm, &format!("look {}", coin)).ok()?.messages.join("\n"))?;
+        coins.push((coin, value));
+    }
+    let order = solve(&equation, &coins)?;
+    Some(order.iter().map(|coin| format!("use {}\n", coin)).collect())
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::synacor_vm::{Outcome, SynacorVm};
+
+/// An instruction ready to execute. Arguments are kept as they are in memory,
+/// literal values or register references, and resolved when executing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instr {
+    Halt,
+    Set(u16, u16),
+    Push(u16),
+    Pop(u16),
+    Eq(u16, u16, u16),
+    Gt(u16, u16, u16),
+    Jmp(u16),
+    Jt(u16, u16),
+    Jf(u16, u16),
+    Add(u16, u16, u16),
+    Mult(u16, u16, u16),
+    Mod(u16, u16, u16),
+    And(u16, u16, u16),
+    Or(u16, u16, u16),
+    Not(u16, u16),
+    Rmem(u16, u16),
+    Wmem(u16, u16),
+    Call(u16),
+    Ret,
+    Out(u16),
+    In(u16),
+    Noop,
+    // not an opcode, a fault when executed
+    Unknown(u16)
+}
+
+impl Instr {
+    /// Decodes the instruction at `address`; arguments past the end of memory read as 0.
+    pub fn decode(memory: &[u16], address: usize) -> Instr {
+        let arg = |i: usize| memory.get(address + i).copied().unwrap_or(0);
+        let (a, b, c) = (arg(1), arg(2), arg(3));
+        match memory[address] {
+            0 => Instr::Halt,
+            1 => Instr::Set(a, b),
+            2 => Instr::Push(a),
+            3 => Instr::Pop(a),
+            4 => Instr::Eq(a, b, c),
+            5 => Instr::Gt(a, b, c),
+            6 => Instr::Jmp(a),
+            7 => Instr::Jt(a, b),
+            8 => Instr::Jf(a, b),
+            9 => Instr::Add(a, b, c),
+            10 => Instr::Mult(a, b, c),
+            11 => Instr::Mod(a, b, c),
+            12 => Instr::And(a, b, c),
+            13 => Instr::Or(a, b, c),
+            14 => Instr::Not(a, b),
+            15 => Instr::Rmem(a, b),
+            16 => Instr::Wmem(a, b),
+            17 => Instr::Call(a),
+            18 => Instr::Ret,
+            19 => Instr::Out(a),
+            20 => Instr::In(a),
+            21 => Instr::Noop,
+            n => Instr::Unknown(n)
+        }
+    }
+
+    /// Number of words taken in memory, so the address of the next instruction
+    /// is `address + size`.
+    pub fn size(self) -> usize {
+        match self {
+            Instr::Halt | Instr::Ret | Instr::Noop | Instr::Unknown(_) => 1,
+            Instr::Push(_) | Instr::Pop(_) | Instr::Jmp(_) | Instr::Call(_) | Instr::Out(_) | Instr::In(_) => 2,
+            Instr::Set(..) | Instr::Jt(..) | Instr::Jf(..) | Instr::Not(..) | Instr::Rmem(..) | Instr::Wmem(..) => 3,
+            _ => 4
+        }
+    }
+
+    /// Whether the instruction may leave ip anywhere but right after itself.
+    pub fn ends_block(self) -> bool {
+        matches!(self, Instr::Halt | Instr::Jmp(_) | Instr::Jt(..) | Instr::Jf(..)
+            | Instr::Call(_) | Instr::Ret | Instr::In(_))
+    }
+}
+
+/// One instruction of a block compiled into a closure, its operands resolved
+/// beforehand. It leaves ip alone unless it jumps or stops the program.
+pub type Op = Box<dyn Fn(&mut SynacorVm) -> Result<(), Outcome> + Send + Sync>;
+
+/// A run of instructions ending at the first one that may jump, or that waits
+/// for input, compiled into closures called one after the other.
+pub struct Block {
+    pub ops: Vec<Op>,
+    // just past the last instruction, where ip goes unless that one jumps
+    pub end: usize,
+    pub jumps: bool
+}
+
+impl fmt::Debug for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Block({} ops to {})", self.ops.len(), self.end)
+    }
+}
+
+// blocks are cut after this many instructions
+const MAX_BLOCK: usize = 64;
+
+/// Blocks compiled so far, by address of their first instruction. Blocks stop
+/// before writes to memory, which go through the interpreter. Words written
+/// after a block was compiled over them drop that block and are never compiled
+/// again, execution there goes through the interpreter instead.
+#[derive(Clone, Debug, Default)]
+pub struct BlockCache {
+    blocks: Vec<Option<Arc<Block>>>,
+    compiled: Vec<bool>,
+    modified: Vec<bool>
+}
+
+impl BlockCache {
+    pub fn new(size: usize) -> BlockCache {
+        BlockCache {
+            blocks: vec![None; size],
+            compiled: vec![false; size],
+            modified: vec![false; size]
+        }
+    }
+
+    /// The block starting at `address`, compiled with `compile` if needed. It
+    /// stops before any address where `stop` holds, and there is none if the
+    /// first word was written or is a write.
+    pub fn get(&mut self, memory: &[u16], address: usize, stop: impl Fn(usize) -> bool,
+               compile: impl Fn(Instr, usize) -> Op) -> Option<&Block> {
+        if self.blocks[address].is_some() {
+            return self.blocks[address].as_deref();
+        }
+        let mut ops = Vec::new();
+        let mut next = address;
+        let mut jumps = false;
+        while next < memory.len() && ops.len() < MAX_BLOCK && (ops.is_empty() || !stop(next)) {
+            let instr = Instr::decode(memory, next);
+            if let Instr::Wmem(..) = instr { break; }
+            if self.modified[next..(next + instr.size()).min(memory.len())].contains(&true) { break; }
+            ops.push(compile(instr, next));
+            next += instr.size();
+            jumps = instr.ends_block();
+            if jumps { break; }
+        }
+        if ops.is_empty() { return None; }
+        let end = next.min(memory.len());
+        for compiled in self.compiled[address..end].iter_mut() {
+            *compiled = true;
+        }
+        self.blocks[address] = Some(Arc::new(Block { ops, end: next, jumps }));
+        self.blocks[address].as_deref()
+    }
+
+    // Drops the blocks going through `word`.
+    fn drop_over(&mut self, word: usize) {
+        for start in word.saturating_sub(MAX_BLOCK * 4)..=word {
+            if self.blocks[start].as_ref().is_some_and(|block| block.end > word) {
+                self.blocks[start] = None;
+            }
+        }
+    }
+
+    /// Drops the blocks covering the words `address..address + len`.
+    pub fn invalidate(&mut self, address: usize, len: usize) {
+        let end = (address + len).min(self.compiled.len());
+        for word in address.min(end)..end {
+            if !self.compiled[word] { continue; }
+            self.modified[word] = true;
+            self.drop_over(word);
+        }
+    }
+
+    /// Drops the blocks going through `address`, so that they get compiled
+    /// again stopping there, as when a hook is put on it.
+    pub fn forget(&mut self, address: usize) {
+        if address < self.blocks.len() {
+            self.drop_over(address);
+        }
+    }
+}
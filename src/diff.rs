@@ -0,0 +1,125 @@
+use std::fmt;
+
+use crate::synacor_vm::SynacorVm;
+
+/// A run of contiguous memory words that changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub start: usize,
+    pub before: Vec<u16>,
+    pub after: Vec<u16>,
+    // addresses of the wmem instructions that last wrote the words, if tracked
+    pub writers: Vec<usize>
+}
+
+impl Change {
+    pub fn end(&self) -> usize {
+        self.start + self.after.len()
+    }
+}
+
+/// Everything that differs between two states of the VM.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diff {
+    pub ip: Option<(usize, usize)>,
+    // (register, before, after)
+    pub registers: Vec<(usize, u16, u16)>,
+    // the stacks past the part they have in common, which is `stack_common` long
+    pub stack_common: usize,
+    pub stack: Option<(Vec<u16>, Vec<u16>)>,
+    pub memory: Vec<Change>
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.ip.is_none() && self.registers.is_empty() && self.stack.is_none() && self.memory.is_empty()
+    }
+}
+
+/// Compares `before` with `after`, a later state of the same program. Changed
+/// words are grouped into runs; the wmem instructions that wrote them are found
+/// if `after` tracks memory writes.
+pub fn diff(before: &SynacorVm, after: &SynacorVm) -> Diff {
+    let mut diff = Diff::default();
+    if before.ip() != after.ip() {
+        diff.ip = Some((before.ip(), after.ip()));
+    }
+    diff.registers = (0..8)
+        .filter(|&r| before.register(r) != after.register(r))
+        .map(|r| (r, before.register(r), after.register(r)))
+        .collect();
+    diff.stack_common = before.stack().iter().zip(after.stack()).take_while(|(a, b)| a == b).count();
+    if before.stack() != after.stack() {
+        diff.stack = Some((before.stack()[diff.stack_common..].to_vec(), after.stack()[diff.stack_common..].to_vec()));
+    }
+
+    let (old, new) = (before.memory(), after.memory());
+    let mut address = 0;
+    while address < old.len().min(new.len()) {
+        if old[address] == new[address] {
+            address += 1;
+            continue;
+        }
+        let start = address;
+        while address < old.len().min(new.len()) && old[address] != new[address] {
+            address += 1;
+        }
+        let mut writers: Vec<usize> = after.memory_writes()
+            .map(|writes| (start..address).filter_map(|a| writes.get(&a).copied()).collect())
+            .unwrap_or_default();
+        writers.sort_unstable();
+        writers.dedup();
+        diff.memory.push(Change { start, before: old[start..address].to_vec(), after: new[start..address].to_vec(), writers });
+    }
+    diff
+}
+
+/// The words as a string if they all look like text: printable ASCII or
+/// newlines, at least two of them.
+pub fn as_text(words: &[u16]) -> Option<String> {
+    if words.len() < 2 || !words.iter().all(|&w| w == 10 || (32..127).contains(&w)) {
+        return None;
+    }
+    Some(words.iter().map(|&w| w as u8 as char).collect())
+}
+
+fn words(words: &[u16]) -> String {
+    match as_text(words) {
+        Some(text) => format!("{:?}", text),
+        None => format!("{:?}", words)
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.after.len() == 1 {
+            write!(f, "[{}]", self.start)?;
+        } else {
+            write!(f, "[{}..{}]", self.start, self.end())?;
+        }
+        write!(f, " {} -> {}", words(&self.before), words(&self.after))?;
+        if !self.writers.is_empty() {
+            let writers: Vec<String> = self.writers.iter().map(usize::to_string).collect();
+            write!(f, " written at {}", writers.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((before, after)) = self.ip {
+            writeln!(f, "ip: {} -> {}", before, after)?;
+        }
+        for &(r, before, after) in self.registers.iter() {
+            writeln!(f, "r{}: {} -> {}", r, before, after)?;
+        }
+        if let Some((before, after)) = &self.stack {
+            writeln!(f, "stack from {}: {:?} -> {:?}", self.stack_common, before, after)?;
+        }
+        for change in self.memory.iter() {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
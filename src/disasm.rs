@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Largest literal value; the words after it up to INVALID name r0 to r7.
+pub const LITERAL: u16 = 32767;
+pub const INVALID: u16 = 32776;
+
+// name and number of arguments, indexed by opcode
+pub const OPCODES: [(&str, usize); 22] = [
+    ("halt", 0), ("set", 2), ("push", 1), ("pop", 1), ("eq", 3), ("gt", 3), ("jmp", 1),
+    ("jt", 2), ("jf", 2), ("add", 3), ("mult", 3), ("mod", 3), ("and", 3), ("or", 3),
+    ("not", 2), ("rmem", 2), ("wmem", 2), ("call", 1), ("ret", 0), ("out", 1), ("in", 1),
+    ("noop", 0)
+];
+
+pub const HALT: u16 = 0;
+pub const SET: u16 = 1;
+pub const PUSH: u16 = 2;
+pub const POP: u16 = 3;
+pub const EQ: u16 = 4;
+pub const GT: u16 = 5;
+pub const JMP: u16 = 6;
+pub const JT: u16 = 7;
+pub const JF: u16 = 8;
+pub const ADD: u16 = 9;
+pub const MULT: u16 = 10;
+pub const MOD: u16 = 11;
+pub const AND: u16 = 12;
+pub const OR: u16 = 13;
+pub const NOT: u16 = 14;
+pub const RMEM: u16 = 15;
+pub const WMEM: u16 = 16;
+pub const CALL: u16 = 17;
+pub const RET: u16 = 18;
+pub const OUT: u16 = 19;
+pub const IN: u16 = 20;
+pub const NOOP: u16 = 21;
+
+/// Register number of an operand, if it names one.
+pub fn register(x: u16) -> Option<usize> {
+    if x > LITERAL && x < INVALID { Some((x - LITERAL) as usize - 1) } else { None }
+}
+
+pub fn reg(r: usize) -> u16 {
+    LITERAL + 1 + r as u16
+}
+
+pub fn literal(x: u16) -> Option<u16> {
+    if x <= LITERAL { Some(x) } else { None }
+}
+
+pub fn operand(x: u16) -> String {
+    match register(x) {
+        Some(r) => format!("r{}", r),
+        None if x <= LITERAL => x.to_string(),
+        None => format!("<{}>", x)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub address: usize,
+    pub opcode: u16,
+    pub args: Vec<u16>
+}
+
+impl Instruction {
+    pub fn name(&self) -> &'static str {
+        OPCODES[self.opcode as usize].0
+    }
+
+    pub fn next(&self) -> usize {
+        self.address + 1 + self.args.len()
+    }
+
+    /// Addresses execution may continue at within the same function: calls are
+    /// stepped over, jumps to a register can't be followed.
+    pub fn successors(&self) -> Vec<usize> {
+        match self.opcode {
+            HALT | RET => vec![],
+            JMP => literal(self.args[0]).map(|a| a as usize).into_iter().collect(),
+            JT | JF => {
+                let mut next = vec![self.next()];
+                next.extend(literal(self.args[1]).map(|a| a as usize));
+                next
+            }
+            _ => vec![self.next()]
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.address, self.name())?;
+        for &arg in self.args.iter() {
+            if self.opcode == OUT && literal(arg).is_some() {
+                write!(f, " {:?}", (arg as u8) as char)?;
+            } else {
+                write!(f, " {}", operand(arg))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The instruction at `address`, if there is a valid opcode with all its arguments.
+pub fn decode(memory: &[u16], address: usize) -> Option<Instruction> {
+    let opcode = *memory.get(address)?;
+    let &(_, count) = OPCODES.get(opcode as usize)?;
+    let args = memory.get(address + 1..address + 1 + count)?.to_vec();
+    Some(Instruction { address, opcode, args })
+}
+
+/// Every instruction reachable from `entry` without following calls, by address.
+pub fn function_body(memory: &[u16], entry: usize) -> BTreeMap<usize, Instruction> {
+    let mut body = BTreeMap::new();
+    let mut seen = HashSet::new();
+    let mut todo = vec![entry];
+    while let Some(address) = todo.pop() {
+        if !seen.insert(address) { continue; }
+        if let Some(instruction) = decode(memory, address) {
+            todo.extend(instruction.successors());
+            body.insert(address, instruction);
+        }
+    }
+    body
+}
+
+/// Every instruction reachable from address 0 or from the target of any
+/// `call <address>` found in memory, following calls as well. Most of the game
+/// is only reached through calls to registers, hence the sweep for call targets.
+pub fn code(memory: &[u16]) -> BTreeMap<usize, Instruction> {
+    let targets = (0..memory.len())
+        .filter_map(|address| decode(memory, address))
+        .filter(|instruction| instruction.opcode == CALL)
+        .filter_map(|instruction| literal(instruction.args[0]))
+        .map(|a| a as usize);
+    walk(memory, targets.chain(Some(0)).collect(), false)
+}
+
+/// `code`, carrying on after every `halt`, `jmp` and `ret` as well: subroutines
+/// only reached through pointers in data, like the item handlers, sit right
+/// after other ones, so this finds them while staying aligned with the code.
+pub fn all_code(memory: &[u16]) -> BTreeMap<usize, Instruction> {
+    walk(memory, code(memory).into_keys().collect(), true)
+}
+
+fn walk(memory: &[u16], mut todo: Vec<usize>, past_ends: bool) -> BTreeMap<usize, Instruction> {
+    let mut code = BTreeMap::new();
+    let mut seen = HashSet::new();
+    while let Some(address) = todo.pop() {
+        if !seen.insert(address) { continue; }
+        if let Some(instruction) = decode(memory, address) {
+            todo.extend(instruction.successors());
+            if instruction.opcode == CALL {
+                todo.extend(literal(instruction.args[0]).map(|a| a as usize));
+            }
+            if past_ends && matches!(instruction.opcode, HALT | JMP | RET) {
+                todo.push(instruction.next());
+            }
+            code.insert(address, instruction);
+        }
+    }
+    code
+}
+
+/// Whether the opcode writes its first argument instead of reading it: `set`,
+/// `pop`, the comparisons, the arithmetic operations, `not`, `rmem` and `in`.
+pub fn writes_first(opcode: u16) -> bool {
+    matches!(opcode, SET | POP | EQ | GT | ADD | MULT | MOD | AND | OR | NOT | RMEM | IN)
+}
+
+/// Whether the instruction reads register `r`.
+pub fn reads(instruction: &Instruction, r: usize) -> bool {
+    let skip = if writes_first(instruction.opcode) { 1 } else { 0 };
+    instruction.args[skip..].contains(&reg(r))
+}
+
+/// Whether the instruction writes register `r`.
+pub fn writes(instruction: &Instruction, r: usize) -> bool {
+    writes_first(instruction.opcode) && instruction.args[0] == reg(r)
+}
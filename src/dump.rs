@@ -0,0 +1,60 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::disasm;
+
+/// One word of memory the way the views of it read: as a number, as the
+/// character `out` would print, as an operand and as code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub address: usize,
+    pub word: u16,
+    // None for words `out` can't print as text
+    pub character: Option<char>,
+    // r0 to r7 for register references, <n> for words that are not valid operands
+    pub operand: Option<String>,
+    // the instruction starting at this word, if one does
+    pub instruction: Option<String>
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let character = self.character.map_or(String::new(), |c| format!("{:?}", c));
+        let line = format!("{:>5}  {:>5}  {:04x}  {:<6}  {:<7}  {}", self.address, self.word, self.word, character,
+                           self.operand.as_deref().unwrap_or(""), self.instruction.as_deref().unwrap_or(""));
+        write!(f, "{}", line.trim_end())
+    }
+}
+
+/// The words of `range`, decoded as instructions one after the other from its
+/// start. Words that are not an instruction are taken for a single word of data.
+pub fn rows(memory: &[u16], range: Range<usize>) -> Vec<Row> {
+    let range = range.start.min(memory.len())..range.end.min(memory.len());
+    let mut next_instruction = range.start;
+    range.map(|address| {
+        let word = memory[address];
+        let mut instruction = None;
+        if address == next_instruction {
+            match disasm::decode(memory, address) {
+                Some(decoded) => {
+                    next_instruction = decoded.next();
+                    let text = decoded.to_string();
+                    instruction = Some(text[text.find(": ").map_or(0, |i| i + 2)..].to_string());
+                }
+                None => next_instruction += 1
+            }
+        }
+        Row {
+            address,
+            word,
+            character: if word == 10 || (32..127).contains(&word) { Some(word as u8 as char) } else { None },
+            operand: if word > 32767 { Some(disasm::operand(word)) } else { None },
+            instruction
+        }
+    }).collect()
+}
+
+/// The rows of `range`, one line each.
+pub fn dump(memory: &[u16], range: Range<usize>) -> String {
+    rows(memory, range).iter().map(|row| format!("{}\n", row)).collect()
+}
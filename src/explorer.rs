@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::ops::Range;
+
+use serde_json::{json, Value};
+
+use crate::fnv::Fnv;
+use crate::game::{self, Room};
+use crate::synacor_vm::SynacorVm;
+
+/// A room as told apart from others with the same name by the state of memory,
+/// or by where the game says the player is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Place {
+    pub id: usize,
+    pub room: Room,
+    pub fingerprint: u64,
+    // commands from the start of the exploration
+    pub path: Vec<String>,
+    // where each exit leads, by id; exits that don't lead to a room are left out
+    pub exits: BTreeMap<String, usize>
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct World {
+    pub places: Vec<Place>
+}
+
+/// Words that change with the command typed rather than with the game state:
+/// the input buffer. Found by typing two nonsense commands of different lengths.
+pub fn volatile_words(vm: &SynacorVm) -> Result<Vec<usize>, String> {
+    let mut short = vm.clone();
+    let mut long = vm.clone();
+    game::play(&mut short, "b").map_err(|outcome| format!("{:?} on a nonsense command", outcome))?;
+    game::play(&mut long, &"a".repeat(40)).map_err(|outcome| format!("{:?} on a nonsense command", outcome))?;
+    Ok((0..vm.memory().len()).filter(|&a| short.memory()[a] != long.memory()[a]).collect())
+}
+
+/// Hash of the room and of memory, leaving out the volatile words.
+pub fn fingerprint(room: &Room, memory: &[u16], volatile: &[usize]) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.text(&room.name);
+    hasher.text(&room.description);
+    let mut from = 0;
+    for &address in volatile.iter().chain(Some(&memory.len())) {
+        hasher.words(&memory[from..address]);
+        from = address + 1;
+    }
+    hasher.finish()
+}
+
+/// Words telling where the player is, found by diffing memory across the
+/// places of `world`, explored from `vm`: outside the input buffer, taking as
+/// many values as there are different rooms, and the same value only in the
+/// same room. At the prompt the game holds the address of the current room
+/// there; look-alike rooms such as the maze have addresses of their own.
+pub fn location_words(vm: &SynacorVm, world: &World) -> Result<Vec<usize>, String> {
+    let volatile = volatile_words(vm)?;
+    let mut memories = Vec::new();
+    for place in world.places.iter() {
+        let mut here = vm.clone();
+        for command in place.path.iter() {
+            game::play(&mut here, command).map_err(|outcome| format!("{:?} on the way to {}", outcome, place.id))?;
+        }
+        memories.push(here.memory().to_vec());
+    }
+    let rooms: HashSet<(&str, &str)> = world.places.iter()
+        .map(|place| (place.room.name.as_str(), place.room.description.as_str()))
+        .collect();
+    Ok((0..vm.memory().len())
+        .filter(|address| !volatile.contains(address))
+        .filter(|&address| {
+            let mut seen: HashMap<u16, (&str, &str)> = HashMap::new();
+            world.places.iter().zip(memories.iter()).all(|(place, memory)| {
+                let room = (place.room.name.as_str(), place.room.description.as_str());
+                *seen.entry(memory[address]).or_insert(room) == room
+            }) && seen.len() >= rooms.len()
+        })
+        .collect())
+}
+
+/// Tries every exit of every room reachable from where `vm` waits for a command,
+/// breadth first, up to `limit` rooms. `vm` is left untouched.
+pub fn explore(vm: &SynacorVm, limit: usize) -> Result<World, String> {
+    let volatile = volatile_words(vm)?;
+    explore_by(vm, limit, |room, vm| fingerprint(room, vm.memory(), &volatile))
+}
+
+/// Explores like `explore`, then again telling places apart by the location
+/// words only, so that each place is a room whatever else changed on the way.
+pub fn explore_rooms(vm: &SynacorVm, limit: usize) -> Result<World, String> {
+    let words = location_words(vm, &explore(vm, limit)?)?;
+    if words.is_empty() { return Err("no word tells the rooms apart".to_string()); }
+    let regions: Vec<Range<usize>> = words.iter().map(|&word| word..word + 1).collect();
+    explore_by(vm, limit, |_, vm| vm.hash_regions(&regions))
+}
+
+// explores with places told apart by `identify`
+fn explore_by(vm: &SynacorVm, limit: usize, identify: impl Fn(&Room, &SynacorVm) -> u64) -> Result<World, String> {
+    let mut start = vm.clone();
+    start.take_output();
+    let room = game::play(&mut start, "look").ok()
+        .and_then(|view| view.room)
+        .ok_or("not in a room")?;
+
+    let mut world = World::default();
+    let mut ids = HashMap::new();
+    let fingerprint_start = identify(&room, &start);
+    ids.insert(fingerprint_start, 0);
+    world.places.push(Place { id: 0, room, fingerprint: fingerprint_start, path: vec![], exits: BTreeMap::new() });
+    let mut queue = VecDeque::new();
+    queue.push_back((0, start));
+    while let Some((id, vm)) = queue.pop_front() {
+        for exit in world.places[id].room.exits.clone() {
+            let mut next = vm.clone();
+            let room = match game::play(&mut next, &exit) {
+                Ok(view) => match view.room {
+                    Some(room) => room,
+                    None => continue
+                },
+                Err(_) => continue
+            };
+            let fingerprint = identify(&room, &next);
+            let next_id = match ids.get(&fingerprint) {
+                Some(&next_id) => next_id,
+                None if world.places.len() >= limit => continue,
+                None => {
+                    let next_id = world.places.len();
+                    let mut path = world.places[id].path.clone();
+                    path.push(exit.clone());
+                    ids.insert(fingerprint, next_id);
+                    world.places.push(Place { id: next_id, room, fingerprint, path, exits: BTreeMap::new() });
+                    queue.push_back((next_id, next));
+                    next_id
+                }
+            };
+            world.places[id].exits.insert(exit, next_id);
+        }
+    }
+    Ok(world)
+}
+
+impl World {
+    pub fn to_json(&self) -> Value {
+        let places: Vec<Value> = self.places.iter()
+            .map(|place| json!({
+                "id": place.id,
+                "name": place.room.name,
+                "description": place.room.description,
+                "items": place.room.items,
+                "fingerprint": format!("{:016x}", place.fingerprint),
+                "path": place.path,
+                "exits": place.exits
+            }))
+            .collect();
+        json!({ "rooms": places })
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph world {\n");
+        for place in self.places.iter() {
+            writeln!(dot, "    {} [label={:?}];", place.id, format!("{} ({})", place.room.name, place.id)).unwrap();
+        }
+        for place in self.places.iter() {
+            for (exit, to) in place.exits.iter() {
+                writeln!(dot, "    {} -> {} [label={:?}];", place.id, to, exit).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,44 @@
+/// FNV-1a over 64 bits. Unlike the standard library's hashers it gives the same
+/// hash for the same words from one build or Rust release to the next, so
+/// fingerprints can be kept and compared across runs.
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv(u64);
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl Default for Fnv {
+    fn default() -> Fnv {
+        Fnv(OFFSET_BASIS)
+    }
+}
+
+impl Fnv {
+    pub fn new() -> Fnv {
+        Fnv::default()
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter() {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(PRIME);
+        }
+    }
+
+    /// Words as little-endian bytes.
+    pub fn words(&mut self, words: &[u16]) {
+        for &word in words.iter() {
+            self.bytes(&word.to_le_bytes());
+        }
+    }
+
+    /// The bytes of `text` and a 0xff after them, which no UTF-8 text holds, so
+    /// that "ab" then "c" doesn't hash like "a" then "bc".
+    pub fn text(&mut self, text: &str) {
+        self.bytes(text.as_bytes());
+        self.bytes(&[0xff]);
+    }
+
+    pub fn finish(self) -> u64 {
+        self.0
+    }
+}
@@ -0,0 +1,117 @@
+use crate::synacor_vm::{Budget, Engine, Outcome, SynacorVm, COMMAND_BUDGET};
+
+const PROMPT: &str = "What do you do?";
+
+/// A room as printed on entering it or on `look`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Room {
+    pub name: String,
+    pub description: String,
+    pub items: Vec<String>,
+    pub exits: Vec<String>
+}
+
+/// What the game printed in answer to one command.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameView {
+    pub room: Option<Room>,
+    // only when the command was `inv`
+    pub inventory: Option<Vec<String>>,
+    // anything else, e.g. "Taken." or what an item looks like
+    pub messages: Vec<String>,
+    // whether it ended asking for the next command
+    pub prompt: bool
+}
+
+enum List {
+    Items,
+    Exits,
+    Inventory
+}
+
+fn list_header(line: &str) -> Option<List> {
+    match line {
+        "Things of interest here:" => Some(List::Items),
+        "Your inventory:" => Some(List::Inventory),
+        "There is 1 exit:" => Some(List::Exits),
+        _ if line.starts_with("There are ") && line.ends_with(" exits:") => Some(List::Exits),
+        _ => None
+    }
+}
+
+impl GameView {
+    pub fn parse(output: &str) -> GameView {
+        let mut view = GameView::default();
+        let mut message = Vec::new();
+        let mut description: Option<Vec<&str>> = None;
+        let mut list = None;
+        for line in output.lines().map(str::trim_end) {
+            if let Some(name) = line.strip_prefix("== ").and_then(|l| l.strip_suffix(" ==")) {
+                view.room = Some(Room { name: name.to_string(), ..Room::default() });
+                description = Some(Vec::new());
+                list = None;
+                continue;
+            }
+            if let Some(header) = list_header(line) {
+                if let (List::Inventory, None) = (&header, &view.inventory) {
+                    view.inventory = Some(Vec::new());
+                }
+                list = Some(header);
+                description = None;
+                continue;
+            }
+            if let (Some(item), Some(kind)) = (line.strip_prefix("- "), &list) {
+                let item = item.to_string();
+                match (kind, view.room.as_mut()) {
+                    (List::Items, Some(room)) => room.items.push(item),
+                    (List::Exits, Some(room)) => room.exits.push(item),
+                    (List::Inventory, _) => view.inventory.get_or_insert_with(Vec::new).push(item),
+                    _ => {}
+                }
+                continue;
+            }
+            list = None;
+            if line == PROMPT {
+                view.prompt = true;
+                description = None;
+            } else if let Some(description) = description.as_mut() {
+                description.push(line);
+            } else if line.is_empty() {
+                if !message.is_empty() {
+                    view.messages.push(message.join("\n"));
+                    message.clear();
+                }
+            } else {
+                message.push(line);
+            }
+            if let (Some(room), Some(description)) = (view.room.as_mut(), &description) {
+                room.description = description.join("\n").trim().to_string();
+            }
+        }
+        if !message.is_empty() {
+            view.messages.push(message.join("\n"));
+        }
+        view
+    }
+}
+
+/// A VM running `program` on the fastest engine, waiting for the first command.
+/// The introduction is left in its output.
+pub fn started(program: &[u16]) -> Result<SynacorVm, String> {
+    let mut vm = SynacorVm::new(program.to_vec());
+    vm.set_engine(Engine::Blocks);
+    match vm.run_budgeted(&Budget::instructions(COMMAND_BUDGET)) {
+        Outcome::NeedInput => Ok(vm),
+        outcome => Err(format!("{:?} before the first prompt", outcome))
+    }
+}
+
+/// Sends one command and parses the answer, which must end at the next prompt.
+pub fn play(vm: &mut SynacorVm, command: &str) -> Result<GameView, Outcome> {
+    vm.feed(command);
+    vm.feed("\n");
+    match vm.run_budgeted(&Budget::instructions(COMMAND_BUDGET)) {
+        Outcome::NeedInput => Ok(GameView::parse(&vm.take_output())),
+        outcome => Err(outcome)
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use crate::codes;
+use crate::explorer::{self, World};
+use crate::game::{self, GameView};
+use crate::synacor_vm::{Outcome, SynacorVm};
+
+/// What one command about an item did, tried on a copy of the game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interaction {
+    pub place: usize,
+    // the last one is the one looked at, those before it set the scene
+    pub commands: Vec<String>,
+    pub messages: Vec<String>,
+    // the game state changed, not just what was printed
+    pub memory_changed: bool,
+    pub new_exits: Vec<String>,
+    // the room the player ended up in, if not the same one
+    pub moved_to: Option<String>,
+    pub codes: Vec<String>,
+    // how the game stopped, if it did not ask for another command
+    pub stopped: Option<Outcome>
+}
+
+impl Interaction {
+    pub fn is_interesting(&self) -> bool {
+        !self.codes.is_empty() || !self.new_exits.is_empty() || self.moved_to.is_some() || self.stopped.is_some()
+    }
+}
+
+impl fmt::Display for Interaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.place, self.commands.join(", then "))?;
+        if let Some(outcome) = self.stopped {
+            write!(f, ", stops the game: {:?}", outcome)?;
+        }
+        for code in self.codes.iter() {
+            write!(f, ", code {}", code)?;
+        }
+        if !self.new_exits.is_empty() {
+            write!(f, ", opens {}", self.new_exits.join(", "))?;
+        }
+        if let Some(room) = &self.moved_to {
+            write!(f, ", moves to {}", room)?;
+        }
+        if self.memory_changed {
+            write!(f, ", changes the game")?;
+        }
+        Ok(())
+    }
+}
+
+fn play(vm: &mut SynacorVm, command: &str) -> Result<GameView, String> {
+    game::play(vm, command).map_err(|outcome| format!("{:?} on {:?}", outcome, command))
+}
+
+/// At every place of `world`, explored from `vm`, tries `take` on the items
+/// lying there, `look` and `use` on those and on the inventory, and `use` on
+/// each item lying there once taken.
+pub fn interactions(vm: &SynacorVm, world: &World) -> Result<Vec<Interaction>, String> {
+    let volatile = explorer::volatile_words(vm)?;
+    let mut interactions = Vec::new();
+    for place in world.places.iter() {
+        let mut here = vm.clone();
+        here.take_output();
+        for command in place.path.iter() {
+            play(&mut here, command)?;
+        }
+        let inventory = play(&mut here.clone(), "inv")?.inventory.unwrap_or_default();
+        let before = explorer::fingerprint(&place.room, here.memory(), &volatile);
+
+        let single = |verb: &str, item: &String| vec![format!("{} {}", verb, item)];
+        let tries = place.room.items.iter().map(|item| single("take", item))
+            .chain(place.room.items.iter().chain(inventory.iter())
+                .flat_map(|item| vec![single("look", item), single("use", item)]))
+            .chain(place.room.items.iter().map(|item| vec![format!("take {}", item), format!("use {}", item)]));
+        for commands in tries {
+            let mut vm = here.clone();
+            let (command, setup) = commands.split_last().unwrap();
+            if setup.iter().any(|command| game::play(&mut vm, command).is_err()) { continue; }
+            let mut interaction = Interaction {
+                place: place.id,
+                commands: commands.clone(),
+                messages: vec![],
+                memory_changed: false,
+                new_exits: vec![],
+                moved_to: None,
+                codes: vec![],
+                stopped: None
+            };
+            let view = match game::play(&mut vm, command) {
+                Ok(view) => view,
+                Err(outcome) => {
+                    interaction.messages.push(vm.take_output());
+                    interaction.codes = codes::find_codes(&interaction.messages[0]);
+                    interaction.stopped = Some(outcome);
+                    interactions.push(interaction);
+                    continue;
+                }
+            };
+            interaction.codes = view.messages.iter().flat_map(|message| codes::find_codes(message)).collect();
+            interaction.messages = view.messages;
+            let room = play(&mut vm, "look")?.room.ok_or("no room after looking around")?;
+            interaction.memory_changed = explorer::fingerprint(&place.room, vm.memory(), &volatile) != before;
+            interaction.new_exits = room.exits.iter().filter(|exit| !place.room.exits.contains(exit)).cloned().collect();
+            if room.name != place.room.name {
+                interaction.moved_to = Some(room.name);
+            }
+            interactions.push(interaction);
+        }
+    }
+    Ok(interactions)
+}
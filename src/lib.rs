@@ -0,0 +1,36 @@
+use std::convert::TryInto;
+use std::fs;
+
+pub mod analysis;
+pub mod codes;
+pub mod coins;
+pub mod decode;
+pub mod diff;
+pub mod disasm;
+pub mod dump;
+pub mod explorer;
+pub mod fnv;
+pub mod game;
+pub mod items;
+pub mod lockstep;
+pub mod scan;
+pub mod solve;
+pub mod strings;
+pub mod synacor_vm;
+pub mod teleporter;
+pub mod vault;
+pub mod walkthrough;
+
+pub fn read_input_u16(path: &str) -> Vec<u16> {
+    bytes_to_u16(&fs::read(path).unwrap())
+}
+
+/// Little-endian words; an odd last byte is read as the low byte of a last word.
+pub fn bytes_to_u16(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks(2)
+        .map(|c| match c.try_into() {
+            Ok(pair) => u16::from_le_bytes(pair),
+            Err(_) => c[0] as u16
+        })
+        .collect()
+}
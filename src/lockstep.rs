@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::disasm;
+use crate::synacor_vm::{Budget, Outcome, SynacorVm};
+
+// executed instructions shown before the one where the VMs differ, and after
+const CONTEXT_BEFORE: usize = 8;
+const CONTEXT_AFTER: usize = 4;
+
+/// The first difference found between two VMs run side by side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    pub step: u64,
+    pub address: usize,
+    pub field: &'static str,
+    pub left: String,
+    pub right: String,
+    // disassembly of the last instructions executed and the next ones, the one
+    // at `address` marked with `>`
+    pub context: Vec<String>
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "step {}, after {}: {} differs", self.step, self.address, self.field)?;
+        writeln!(f, "  left:  {}", self.left)?;
+        writeln!(f, "  right: {}", self.right)?;
+        for line in self.context.iter() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+fn differ<T: fmt::Debug + PartialEq>(field: &'static str, left: T, right: T) -> Option<(&'static str, String, String)> {
+    if left == right { None } else { Some((field, format!("{:?}", left), format!("{:?}", right))) }
+}
+
+// First part of the state where the VMs differ. Of memory, only the word at
+// `word` is looked at, or all of it when that is None.
+fn compare(left: &SynacorVm, right: &SynacorVm, word: Option<usize>) -> Option<(&'static str, String, String)> {
+    differ("ip", left.ip(), right.ip())
+        .or_else(|| (0..8).find_map(|r| differ("registers", left.register(r), right.register(r))
+            .map(|(field, a, b)| (field, format!("r{} = {}", r, a), format!("r{} = {}", r, b)))))
+        .or_else(|| differ("stack", left.stack(), right.stack()))
+        .or_else(|| differ("output", left.output(), right.output()))
+        .or_else(|| differ("memory length", left.memory().len(), right.memory().len()))
+        .or_else(|| {
+            let address = match word {
+                Some(address) => address,
+                None if left.memory() == right.memory() => return None,
+                None => (0..left.memory().len()).find(|&a| left.memory()[a] != right.memory()[a])?
+            };
+            let (a, b) = (left.memory().get(address)?, right.memory().get(address)?);
+            differ("memory", a, b).map(|(field, a, b)| (field, format!("[{}] = {}", address, a), format!("[{}] = {}", address, b)))
+        })
+}
+
+// Address the next instruction writes to if it is a `wmem`, or else one past
+// the end of memory so no word gets compared.
+fn written(vm: &SynacorVm) -> usize {
+    match disasm::decode(vm.memory(), vm.ip()) {
+        Some(instruction) if instruction.opcode == disasm::WMEM => {
+            let target = instruction.args[0];
+            disasm::register(target).map_or(target, |r| vm.register(r)) as usize
+        }
+        _ => vm.memory().len()
+    }
+}
+
+fn line(memory: &[u16], address: usize, marked: bool) -> String {
+    let marker = if marked { ">" } else { " " };
+    match disasm::decode(memory, address) {
+        Some(instruction) => format!("{} {}", marker, instruction),
+        None => format!("{} {}: {}", marker, address, memory.get(address).map_or("-".to_string(), |w| w.to_string()))
+    }
+}
+
+// Keeps the addresses of the last instructions executed, for the context.
+fn remember(executed: &mut VecDeque<usize>, addresses: &[usize]) {
+    for &address in addresses.iter() {
+        executed.push_back(address);
+        if executed.len() > CONTEXT_BEFORE { executed.pop_front(); }
+    }
+}
+
+fn mismatch(vm: &SynacorVm, executed: &VecDeque<usize>, step: u64, address: usize,
+            (field, left, right): (&'static str, String, String)) -> Mismatch {
+    let memory = vm.memory();
+    let mut context: Vec<String> = executed.iter().map(|&a| line(memory, a, false)).collect();
+    context.push(line(memory, address, true));
+    let mut next = vm.ip();
+    for _ in 0..CONTEXT_AFTER {
+        context.push(line(memory, next, false));
+        next = disasm::decode(memory, next).map_or(next + 1, |i| i.next());
+    }
+    Mismatch { step, address, field, left, right, context }
+}
+
+/// Runs both VMs side by side until `max_steps` instructions were executed: `right`
+/// a step at a time, a whole block with Engine::Blocks, and `left` through as
+/// many instructions one at a time. Compares ip, registers, stack and output
+/// after each step of `right`. Memory being large, only the words written by
+/// `wmem` are compared then, and all of it at the end. Whenever both wait for
+/// input they get the next command. Returns how many instructions were executed
+/// if they never differed.
+pub fn lockstep(left: &mut SynacorVm, right: &mut SynacorVm, commands: &[&str], max_steps: u64) -> Result<u64, Mismatch> {
+    let step_budget = Budget::instructions(1);
+    let mut commands = commands.iter();
+    let mut executed = VecDeque::new();
+    let mut step = 0;
+    while step < max_steps {
+        let steps = right.steps();
+        let right_outcome = right.step();
+        let ran = right.steps() - steps;
+        // steps leave out hooks and the instruction that stopped the program
+        let count = if right_outcome == Outcome::BudgetExhausted { ran.max(1) } else { ran + 1 };
+        let mut addresses = Vec::new();
+        let mut written_words = Vec::new();
+        let mut left_outcome = Outcome::BudgetExhausted;
+        for _ in 0..count {
+            addresses.push(left.ip());
+            written_words.push(written(left));
+            left_outcome = left.run_budgeted(&step_budget);
+            if left_outcome != Outcome::BudgetExhausted { break; }
+        }
+        let difference = differ("outcome", left_outcome, right_outcome)
+            .or_else(|| written_words.iter().find_map(|&word| compare(left, right, Some(word))));
+        let (&last, before) = addresses.split_last().unwrap();
+        remember(&mut executed, before);
+        if let Some(difference) = difference {
+            return Err(mismatch(left, &executed, step + before.len() as u64, last, difference));
+        }
+        step += before.len() as u64;
+        // the instruction waiting for input or stopping the program is not a step
+        if left_outcome == Outcome::BudgetExhausted {
+            remember(&mut executed, &[last]);
+            step += 1;
+        }
+        match left_outcome {
+            Outcome::BudgetExhausted => {}
+            Outcome::NeedInput => match commands.next() {
+                Some(command) => {
+                    left.feed(&format!("{}\n", command));
+                    right.feed(&format!("{}\n", command));
+                }
+                None => break
+            },
+            _ => break
+        }
+    }
+    // hooks may have written memory too
+    match compare(left, right, None) {
+        Some(difference) => Err(mismatch(left, &executed, step, left.ip(), difference)),
+        None => Ok(step)
+    }
+}
@@ -1,108 +1,223 @@
-use std::convert::TryInto;
+use std::env;
 use std::fs;
+use std::process;
 
-use synacor_vm::SynacorVm;
+use synacor_challenge::{analysis, diff, disasm, dump, explorer, game, items, read_input_u16, scan, solve, strings, teleporter, vault, walkthrough};
+use synacor_challenge::synacor_vm::{SynacorVm, TELEPORTER_R7};
+use synacor_challenge::teleporter::Bypass;
 
-mod synacor_vm;
+fn main() {
+    let bin_input = read_input_u16("input/challenge.bin");
+    match env::args().nth(1).as_deref() {
+        Some("r7") => find_r7(&bin_input),
+        Some("bypass") => find_bypass(&bin_input),
+        Some("registers") => match env::args().nth(2).as_deref() {
+            Some("run") => registers_read(bin_input),
+            _ => registers(&bin_input)
+        },
+        Some("explore") => explore(bin_input, env::args().nth(2).as_deref(), false),
+        Some("rooms") => explore(bin_input, env::args().nth(2).as_deref(), true),
+        Some("items") => items(bin_input),
+        Some("strings") => show_strings(&bin_input, env::args().nth(2).as_deref()),
+        Some("dump") => show_dump(bin_input, env::args().nth(2).as_deref(), env::args().nth(3).as_deref()),
+        Some("find") => find(&bin_input, &env::args().skip(2).collect::<Vec<_>>().join(" ")),
+        Some("diff") => show_diff(bin_input, &env::args().skip(2).collect::<Vec<_>>().join(" ")),
+        Some("solve") => solve(&bin_input),
+        Some("vault") => solve_vault(env::args().nth(2).as_deref().unwrap_or("input/vault.json")),
+        Some("replay") => replay(&bin_input, &env::args().nth(2).expect("usage: replay <script>")),
+        Some("memo") => { walkthrough(memoized(bin_input)); }
+        Some("native") => { walkthrough(native(bin_input)); }
+        _ => { walkthrough(bypassed(bin_input)); }
+    }
+}
 
-pub fn read_input_u16(path: &str) -> Vec<u16> {
-    let bin_input = fs::read(path).unwrap();
-    bin_input.chunks(2)
-        .map(|c| u16::from_le_bytes(c.try_into().unwrap()))
-        .collect()
+// the confirmation is skipped altogether
+fn bypassed(bin_input: Vec<u16>) -> SynacorVm {
+    let site = teleporter::find_call_site(&bin_input).expect("no call to the confirmation routine");
+    let bypass = Bypass::new(&site);
+    let mut vm = SynacorVm::new(bin_input);
+    vm.patch(bypass.address, &bypass.words);
+    vm
 }
 
-fn main() {
-    let bin_input = read_input_u16("input/challenge.bin");
+// the confirmation runs, with the results of pure subroutines cached
+fn memoized(bin_input: Vec<u16>) -> SynacorVm {
     let mut vm = SynacorVm::new(bin_input);
-    let prepared = r#"doorway
-north
-north
-bridge
-continue
-down
-east
-take empty lantern
-west
-west
-passage
-ladder
-west
-south
-north
-take can
-use can
-use lantern
-west
-ladder
-darkness
-continue
-west
-west
-west
-west
-north
-take red coin
-north
-east
-take concave coin
-down
-take corroded coin
-up
-west
-west
-take blue coin
-up
-take shiny coin
-down
-east
-use blue coin
-use red coin
-use shiny coin
-use concave coin
-use corroded coin
-north
-take teleporter
-use teleporter
-take business card
-"#;
-    let second_prepared = r#"north
-north
-north
-north
-north
-north
-north
-north
-north
-take orb
-north
-east
-east
-north
-west
-south
-east
-east
-west
-north
-north
-east
-vault
-take mirror
-use mirror
-"#;
-    vm.run(prepared, second_prepared);
-}
-
-/* python z3
-from z3 import *
-slots = [Int("s_%s" % i) for i in range(5)]
-values = [Or(And(slots[i] < 10, slots[i] > 1, slots[i] % 2 == 1), slots[i] == 2) for i in range(5)]
-distinction = Distinct(slots)
-solve(values + [distinction, slots[0] + slots[1] * slots[2] ** 2 + slots[3] ** 3 - slots[4] == 399])
-[s_0 = 9, s_1 = 2, s_2 = 5, s_3 = 7, s_4 = 3]
-*/
+    println!("memoizing {} pure subroutines", vm.memoize_pure_functions());
+    vm
+}
+
+fn registers(bin_input: &[u16]) {
+    for usage in analysis::register_usage(bin_input) {
+        println!("r{}", usage.register);
+        for instruction in usage.reads.iter() {
+            println!("  read by {}", instruction);
+        }
+        for instruction in usage.writes.iter() {
+            println!("  written by {}", instruction);
+        }
+    }
+}
+
+fn registers_read(bin_input: Vec<u16>) {
+    let mut vm = bypassed(bin_input);
+    vm.track_register_reads();
+    let vm = walkthrough(vm);
+    for (r, reads) in vm.register_reads().unwrap().iter().enumerate() {
+        println!("r{} read during the run", r);
+        for (&address, count) in reads.iter() {
+            if let Some(instruction) = disasm::decode(vm.memory(), address) {
+                println!("  {} ({} times)", instruction, count);
+            }
+        }
+    }
+}
+
+fn find_r7(bin_input: &[u16]) {
+    let site = teleporter::find_call_site(bin_input).expect("no call to the confirmation routine");
+    println!("{}", site);
+    for r7 in teleporter::find_r7(&site) {
+        println!("r7: {}", r7);
+    }
+}
+
+fn find_bypass(bin_input: &[u16]) {
+    for instruction in analysis::register_usage(bin_input)[7].reads.iter() {
+        println!("reads r7: {}", instruction);
+    }
+    for function in teleporter::confirmation_routines(bin_input) {
+        println!("confirmation routine: {}", function);
+    }
+    let site = teleporter::find_call_site(bin_input).expect("no call to the confirmation routine");
+    println!("{}", site);
+    println!("{}", Bypass::new(&site));
+}
+
+// the confirmation runs natively, once checked against the guest on small inputs
+fn native(bin_input: Vec<u16>) -> SynacorVm {
+    let site = teleporter::find_call_site(&bin_input).expect("no call to the confirmation routine");
+    let mismatches = teleporter::check_native(&bin_input, site.function);
+    if !mismatches.is_empty() {
+        panic!("native confirmation differs from the guest for (r0, r1, r7) in {:?}", mismatches);
+    }
+    println!("native confirmation matches the guest at {}", site.function);
+    let mut vm = SynacorVm::new(bin_input);
+    vm.hook(site.function, teleporter::native_confirmation);
+    vm
+}
+
+fn started(bin_input: &[u16]) -> SynacorVm {
+    let mut vm = game::started(bin_input).unwrap_or_else(|e| panic!("{}", e));
+    vm.take_output();
+    vm
+}
+
+fn explore(bin_input: Vec<u16>, format: Option<&str>, by_location: bool) {
+    let vm = started(&bin_input);
+    let world = if by_location { explorer::explore_rooms(&vm, 1000) } else { explorer::explore(&vm, 1000) }
+        .unwrap_or_else(|e| panic!("{}", e));
+    match format {
+        Some("dot") => print!("{}", world.to_dot()),
+        _ => println!("{}", serde_json::to_string_pretty(&world.to_json()).unwrap())
+    }
+}
+
+fn items(bin_input: Vec<u16>) {
+    let vm = started(&bin_input);
+    let world = explorer::explore(&vm, 1000).unwrap_or_else(|e| panic!("{}", e));
+    let interactions = items::interactions(&vm, &world).unwrap_or_else(|e| panic!("{}", e));
+    for interaction in interactions.iter().filter(|i| i.is_interesting() || i.memory_changed) {
+        println!("{} ({})", interaction, world.places[interaction.place].room.name);
+    }
+}
+
+// memory from `start` to `end`, 16 words by default, as it is at the first prompt
+fn show_dump(bin_input: Vec<u16>, start: Option<&str>, end: Option<&str>) {
+    let start: usize = start.and_then(|s| s.parse().ok()).expect("usage: dump <start> [end]");
+    let end = end.and_then(|s| s.parse().ok()).unwrap_or(start + 16);
+    print!("{}", dump::dump(started(&bin_input).memory(), start..end));
+}
+
+// every string of the game, or those containing `search` whatever the case
+fn show_strings(bin_input: &[u16], search: Option<&str>) {
+    let search = search.map(str::to_lowercase);
+    for entry in strings::strings(bin_input).unwrap_or_else(|e| panic!("{}", e)) {
+        if search.as_ref().is_none_or(|search| entry.text.to_lowercase().contains(search)) {
+            println!("{}", entry);
+        }
+    }
+}
+
+fn find(bin_input: &[u16], text: &str) {
+    for address in scan::find_text(bin_input, text) {
+        println!("{}: {:?}", address, text);
+    }
+    for address in scan::find_counted_text(bin_input, text) {
+        println!("{}: {:?} with its length", address, text);
+    }
+}
+
+// what typing `command` at the start of the game changes
+fn show_diff(bin_input: Vec<u16>, command: &str) {
+    let mut before = started(&bin_input);
+    before.track_memory_writes();
+    let mut after = before.clone();
+    let view = game::play(&mut after, command).unwrap_or_else(|outcome| panic!("{:?}", outcome));
+    for message in view.messages.iter() {
+        println!("{}", message);
+    }
+    print!("{}", diff::diff(&before, &after));
+}
+
+fn solve(bin_input: &[u16]) {
+    let spec = fs::read_to_string("support/arch-spec").unwrap_or_default();
+    match solve::solve(bin_input, &spec) {
+        Ok(solution) => {
+            print!("{}", solution.script);
+            for code in solution.codes.iter() {
+                eprintln!("code: {}", code);
+            }
+        }
+        Err(e) => {
+            eprintln!("solve: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn replay(bin_input: &[u16], path: &str) {
+    let script = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    match solve::replay(bin_input, &script) {
+        Ok((output, codes)) => {
+            print!("{}", output);
+            for code in codes.iter() {
+                println!("code: {}", code);
+            }
+        }
+        Err(e) => {
+            eprintln!("replay: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn walkthrough(mut vm: SynacorVm) -> SynacorVm {
+    let (prepared, second_prepared) = walkthrough::scripts(&vm, TELEPORTER_R7).unwrap_or_else(|e| panic!("{}", e));
+    vm.run(&prepared, &second_prepared);
+    vm
+}
+
+// the commands through a vault grid described in JSON
+fn solve_vault(path: &str) {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    match vault::Grid::from_json(&text).and_then(|grid| vault::grid_commands(&grid)) {
+        Ok(commands) => print!("{}", commands),
+        Err(e) => {
+            eprintln!("vault: {}", e);
+            process::exit(1);
+        }
+    }
+}
 
 /* The strange book
 The cover of this book subtly swirls with colors.  It is titled "A Brief Introduction to Interdimensional Physics".  It reads:
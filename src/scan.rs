@@ -0,0 +1,96 @@
+use crate::synacor_vm::SynacorVm;
+
+/// What a candidate word must have done since the last snapshot to stay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Predicate {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    EqualTo(u16)
+}
+
+impl Predicate {
+    fn holds(self, before: u16, after: u16) -> bool {
+        match self {
+            Predicate::Changed => before != after,
+            Predicate::Unchanged => before == after,
+            Predicate::Increased => after > before,
+            Predicate::Decreased => after < before,
+            Predicate::EqualTo(value) => after == value
+        }
+    }
+}
+
+/// Addresses where `pattern` starts in memory.
+pub fn find(memory: &[u16], pattern: &[u16]) -> Vec<usize> {
+    if pattern.is_empty() { return Vec::new(); }
+    memory.windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| *window == pattern)
+        .map(|(address, _)| address)
+        .collect()
+}
+
+/// Addresses of `text` stored one character per word.
+pub fn find_text(memory: &[u16], text: &str) -> Vec<usize> {
+    let pattern: Vec<u16> = text.chars().map(|c| c as u16).collect();
+    find(memory, &pattern)
+}
+
+/// Addresses of `text` stored as the game does, its length in the first word.
+pub fn find_counted_text(memory: &[u16], text: &str) -> Vec<usize> {
+    let pattern: Vec<u16> = Some(text.chars().count() as u16).into_iter()
+        .chain(text.chars().map(|c| c as u16))
+        .collect();
+    find(memory, &pattern)
+}
+
+/// Candidate words narrowed down snapshot after snapshot, the way memory
+/// scanners for games find where a value is kept.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scan {
+    // with the value each had in the last snapshot
+    candidates: Vec<(usize, u16)>
+}
+
+impl Scan {
+    /// Every word of memory.
+    pub fn all(vm: &SynacorVm) -> Scan {
+        Scan::at(vm, 0..vm.memory().len())
+    }
+
+    /// The words holding `value`.
+    pub fn value(vm: &SynacorVm, value: u16) -> Scan {
+        Scan::at(vm, find(vm.memory(), &[value]))
+    }
+
+    /// The given words, such as the results of `find_text`.
+    pub fn at(vm: &SynacorVm, addresses: impl IntoIterator<Item = usize>) -> Scan {
+        let memory = vm.memory();
+        Scan { candidates: addresses.into_iter().filter(|&a| a < memory.len()).map(|a| (a, memory[a])).collect() }
+    }
+
+    /// Keeps the candidates for which `predicate` holds between the last
+    /// snapshot and `vm`, which becomes the last snapshot.
+    pub fn narrow(&mut self, vm: &SynacorVm, predicate: Predicate) -> &mut Scan {
+        let memory = vm.memory();
+        self.candidates.retain(|&(address, before)| memory.get(address).is_some_and(|&after| predicate.holds(before, after)));
+        for candidate in self.candidates.iter_mut() {
+            candidate.1 = memory[candidate.0];
+        }
+        self
+    }
+
+    pub fn candidates(&self) -> Vec<usize> {
+        self.candidates.iter().map(|&(address, _)| address).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
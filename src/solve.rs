@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+
+use crate::codes::{self, Code, CodeDetector};
+use crate::coins;
+use crate::explorer::{self, World};
+use crate::game::{self, GameView};
+use crate::synacor_vm::{Budget, Outcome, SynacorVm, COMMAND_BUDGET};
+use crate::teleporter::{self, Bypass};
+use crate::vault;
+
+/// Codes there are to find, the one in the architecture spec included.
+pub const CODE_COUNT: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    // see replay for the lines starting with '!'
+    pub script: String,
+    pub codes: Vec<Code>
+}
+
+fn started(program: &[u16]) -> Result<SynacorVm, String> {
+    game::started(program).map_err(|e| format!("start: {}", e))
+}
+
+/// Plays `script` from the start of `program`. Lines are commands, except
+/// `!patch <address>: <words>` which patches memory and `!r7 <value>` which
+/// sets r7. Returns everything printed and the codes found in it.
+pub fn replay(program: &[u16], script: &str) -> Result<(String, Vec<Code>), String> {
+    let mut vm = started(program)?;
+    let mut detector = CodeDetector::new();
+    let mut output = vm.take_output();
+    detector.scan(&output);
+    for (number, line) in script.lines().enumerate() {
+        let error = |message: &str| format!("line {} {:?}: {}", number + 1, line, message);
+        if let Some(patch) = line.strip_prefix("!patch ") {
+            let (address, words) = patch.split_once(": ").ok_or_else(|| error("expected !patch <address>: <words>"))?;
+            let address = address.parse().map_err(|_| error("bad address"))?;
+            let words = words.split(',').map(|w| w.parse()).collect::<Result<Vec<u16>, _>>()
+                .map_err(|_| error("bad words"))?;
+            if address + words.len() > vm.memory().len() { return Err(error("patch past the end of memory")); }
+            vm.patch(address, &words);
+        } else if let Some(r7) = line.strip_prefix("!r7 ") {
+            vm.set_register(7, r7.parse().map_err(|_| error("bad value"))?);
+        } else {
+            detector.command(line);
+            vm.feed(line);
+            vm.feed("\n");
+            let outcome = vm.run_budgeted(&Budget::instructions(COMMAND_BUDGET));
+            let text = vm.take_output();
+            detector.scan(&text);
+            output.push_str(&text);
+            if outcome != Outcome::NeedInput { return Err(error(&format!("{:?}", outcome))); }
+        }
+    }
+    Ok((output, detector.codes().to_vec()))
+}
+
+// The game being played from the start, with the commands played so far,
+// the codes they printed and what was tried already.
+struct Player {
+    vm: SynacorVm,
+    script: String,
+    codes: HashSet<String>,
+    held: HashSet<String>,
+    // room and item
+    taken: HashSet<(String, String)>,
+    vault_opened: bool
+}
+
+impl Player {
+    fn command(&mut self, command: &str) -> Result<GameView, String> {
+        let view = game::play(&mut self.vm, command).map_err(|outcome| format!("{:?} on {:?}", outcome, command))?;
+        self.script.push_str(command);
+        self.script.push('\n');
+        self.codes.extend(codes_in(&view));
+        Ok(view)
+    }
+
+    fn commands(&mut self, commands: &str) -> Result<(), String> {
+        for command in commands.lines() {
+            self.command(command)?;
+        }
+        Ok(())
+    }
+
+    fn walk(&mut self, path: &[String]) -> Result<(), String> {
+        for command in path.iter() {
+            self.command(command)?;
+        }
+        Ok(())
+    }
+
+    // Makes one move towards the codes, in the world explored from here: opens
+    // the vault once its antechamber is in reach, puts the coins in once they
+    // are all at hand, takes the items not taken yet, or else uses the first
+    // item that prints a new code, brings an item never held before or leads
+    // out of this world, with r7 set to `r7` if only that does. Returns whether
+    // there was a move to make.
+    fn advance(&mut self, r7: u16) -> Result<bool, String> {
+        let world = explorer::explore(&self.vm, EXPLORE_LIMIT).map_err(|e| format!("explore: {}", e))?;
+        let antechamber = world.places.iter().find(|place| vault::pedestal(&place.room).is_some());
+
+        if let (Some(place), false) = (antechamber, self.vault_opened) {
+            self.walk(&place.path)?;
+            let commands = vault::commands(&self.vm).map_err(|e| format!("vault: {}", e))?;
+            self.commands(&commands)?;
+            self.vault_opened = true;
+            return Ok(true);
+        }
+
+        for place in world.places.iter().filter(|place| coins::Equation::find(&place.room.description).is_some()) {
+            let mut there = self.vm.clone();
+            if place.path.iter().any(|command| game::play(&mut there, command).is_err()) { continue; }
+            if let Some(commands) = coins::coin_commands(&there) {
+                self.walk(&place.path)?;
+                self.commands(&commands)?;
+                return Ok(true);
+            }
+        }
+
+        // taking items leaves the map as it is, so they are all taken in one go,
+        // the orb excepted, which is left on its pedestal for the vault
+        let untaken = |taken: &HashSet<(String, String)>, id: usize| {
+            let room = &world.places[id].room;
+            Some(id) != antechamber.map(|place| place.id)
+                && room.items.iter().any(|item| !taken.contains(&(room.name.clone(), item.clone())))
+        };
+        let (mut here, mut took) = (0, false);
+        while let Some((id, path)) = routes(&world, here).into_iter().find(|&(id, _)| untaken(&self.taken, id)) {
+            self.walk(&path)?;
+            let room = &world.places[id].room;
+            for item in room.items.iter() {
+                if self.taken.insert((room.name.clone(), item.clone())) {
+                    self.command(&format!("take {}", item))?;
+                }
+            }
+            here = id;
+            took = true;
+        }
+        if took {
+            return Ok(true);
+        }
+
+        let rooms: HashSet<&str> = world.places.iter().map(|place| place.room.name.as_str()).collect();
+        let items = inventory(&self.vm)?;
+        self.held.extend(items.iter().cloned());
+        for &r7 in [None, Some(r7)].iter() {
+            for item in items.iter() {
+                let command = format!("use {}", item);
+                let mut vm = self.vm.clone();
+                if let Some(r7) = r7 {
+                    vm.set_register(7, r7);
+                }
+                let view = match game::play(&mut vm, &command) {
+                    Ok(view) => view,
+                    Err(_) => continue
+                };
+                let new_code = codes_in(&view).iter().any(|code| !self.codes.contains(code));
+                let moved_out = view.room.as_ref().is_some_and(|room| !rooms.contains(room.name.as_str()));
+                let new_item = inventory(&vm)?.iter().any(|item| !self.held.contains(item));
+                if new_code || moved_out || new_item {
+                    if let Some(r7) = r7 {
+                        self.vm.set_register(7, r7);
+                        self.script.push_str(&format!("!r7 {}\n", r7));
+                    }
+                    self.command(&command)?;
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+// Every place of `world` with the exits leading there from `from`, nearest first.
+fn routes(world: &World, from: usize) -> Vec<(usize, Vec<String>)> {
+    let mut routes = vec![(from, vec![])];
+    let mut seen = HashSet::new();
+    seen.insert(from);
+    let mut i = 0;
+    while i < routes.len() {
+        let (id, path) = routes[i].clone();
+        for (exit, &next) in world.places[id].exits.iter() {
+            if seen.insert(next) {
+                let mut path = path.clone();
+                path.push(exit.clone());
+                routes.push((next, path));
+            }
+        }
+        i += 1;
+    }
+    routes
+}
+
+fn codes_in(view: &GameView) -> Vec<String> {
+    view.messages.iter()
+        .chain(view.room.as_ref().map(|room| &room.description))
+        .flat_map(|text| codes::find_codes(text))
+        .collect()
+}
+
+// the items in the inventory, leaving the game as it is
+fn inventory(vm: &SynacorVm) -> Result<Vec<String>, String> {
+    game::play(&mut vm.clone(), "inv")
+        .map_err(|outcome| format!("{:?} on inv", outcome))?
+        .inventory.ok_or_else(|| "no inventory".to_string())
+}
+
+// places the explorer looks through for the next room to go to
+const EXPLORE_LIMIT: usize = 1000;
+
+/// Works out a script collecting every code from a fresh `program`, with the
+/// confirmation of the teleporter bypassed. `spec` is the architecture spec,
+/// for the code printed in it. Searches every r7 for the one the teleporter wants.
+pub fn solve(program: &[u16], spec: &str) -> Result<Solution, String> {
+    let site = teleporter::find_call_site(program).ok_or("teleporter: no call to the confirmation routine")?;
+    let r7 = *teleporter::find_r7(&site).first().ok_or("teleporter: no r7 passes the confirmation")?;
+    solve_with_r7(program, spec, r7)
+}
+
+/// Like `solve`, with the r7 the teleporter wants already known. Plays from
+/// the start while there is a move to make, see Player::advance: nothing about
+/// the game is known beforehand but the vault, the monument and the items.
+pub fn solve_with_r7(program: &[u16], spec: &str, r7: u16) -> Result<Solution, String> {
+    let mut vm = started(program)?;
+    vm.take_output();
+    let site = teleporter::find_call_site(program).ok_or("teleporter: no call to the confirmation routine")?;
+    let bypass = Bypass::new(&site);
+    vm.patch(bypass.address, &bypass.words);
+    let mut player = Player {
+        vm,
+        script: format!("!{}\n", bypass),
+        codes: HashSet::new(),
+        held: HashSet::new(),
+        taken: HashSet::new(),
+        vault_opened: false
+    };
+    while player.advance(r7)? {}
+
+    let script = player.script;
+    let (_, mut codes) = replay(program, &script).map_err(|e| format!("replay: {}", e))?;
+    for text in codes::find_codes(spec) {
+        if !codes.iter().any(|code| code.text == text) {
+            codes.insert(0, Code { text, mirrored: false });
+        }
+    }
+    if codes.len() != CODE_COUNT {
+        let found: Vec<_> = codes.iter().map(|code| code.submission()).collect();
+        return Err(format!("found {} codes out of {}: {}", codes.len(), CODE_COUNT, found.join(", ")));
+    }
+    Ok(Solution { script, codes })
+}
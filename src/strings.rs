@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::disasm::{self, Instruction, CALL, RMEM};
+use crate::synacor_vm::{Budget, Outcome, SynacorVm};
+
+// instructions a string reference may take to set up its registers before the call
+const MAX_SETUP: usize = 4;
+const PRINT_BUDGET: u64 = 100_000;
+// shorter runs of printable words are taken for data rather than text
+const MIN_PLAIN: usize = 3;
+
+fn printable(word: u16) -> bool {
+    word == 10 || (32..127).contains(&word)
+}
+
+/// A string of the game and where it was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub address: usize,
+    pub text: String,
+    // the call printing it, when decoded by emulating that call
+    pub reference: Option<usize>
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}", self.address, self.text)?;
+        if let Some(reference) = self.reference {
+            write!(f, " (printed at {})", reference)?;
+        }
+        Ok(())
+    }
+}
+
+fn call_targets(code: &BTreeMap<usize, Instruction>) -> Vec<usize> {
+    let mut targets: Vec<usize> = code.values()
+        .filter(|instruction| instruction.opcode == CALL)
+        .filter_map(|instruction| disasm::literal(instruction.args[0]))
+        .map(|target| target as usize)
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+    targets
+}
+
+/// The routine walking a length-prefixed string: it reads the length at r0 and
+/// calls the routine in r1 for each character.
+pub fn string_routine(memory: &[u16]) -> Option<usize> {
+    call_targets(&disasm::code(memory)).into_iter().find(|&entry| {
+        let body = disasm::function_body(memory, entry);
+        body.values().any(|i| i.opcode == RMEM && i.args[1] == disasm::reg(0))
+            && body.values().any(|i| i.opcode == CALL && disasm::register(i.args[0]).is_some())
+    })
+}
+
+// The string routine and the short routines calling it with their own r1.
+fn printing_routines(memory: &[u16], code: &BTreeMap<usize, Instruction>, routine: usize) -> Vec<usize> {
+    let mut routines = vec![routine];
+    routines.extend(call_targets(code).into_iter().filter(|&entry| {
+        let body = disasm::function_body(memory, entry);
+        body.len() <= 8 && body.values().any(|i| i.opcode == CALL && i.args[0] as usize == routine)
+    }));
+    routines
+}
+
+// Where the instructions right before `call` that only combine literals into
+// registers start, and how many there are, if they set r0.
+fn setup(code: &BTreeMap<usize, Instruction>, call: &Instruction) -> Option<(usize, u64)> {
+    let mut start = call.address;
+    let mut count = 0;
+    let mut sets_r0 = false;
+    while count < MAX_SETUP {
+        let previous = match code.range(..start).next_back() {
+            Some((_, previous)) if previous.next() == start => previous,
+            _ => break
+        };
+        // not `pop` or `in`, which take nothing, nor `rmem`, which reads memory
+        let combines_literals = disasm::writes_first(previous.opcode)
+            && previous.args.len() > 1 && previous.opcode != RMEM
+            && previous.args[1..].iter().all(|&arg| disasm::literal(arg).is_some());
+        if !combines_literals { break; }
+        sets_r0 |= previous.args[0] == disasm::reg(0);
+        start = previous.address;
+        count += 1;
+    }
+    if sets_r0 { Some((start, count as u64)) } else { None }
+}
+
+// Runs the setup and the call on their own and returns what gets printed and
+// the string address r0 held at the call.
+fn emulate(memory: &[u16], (start, count): (usize, u64), call: &Instruction) -> Option<(usize, String)> {
+    let mut vm = SynacorVm::new(memory.to_vec());
+    // back from the call, returning from the hook with an empty stack halts
+    vm.hook(call.next(), |_| {});
+    vm.jump(start);
+    vm.run_budgeted(&Budget::instructions(count));
+    if vm.ip() != call.address { return None; }
+    let address = vm.register(0) as usize;
+    match vm.run_budgeted(&Budget::instructions(PRINT_BUDGET)) {
+        Outcome::Halted if !vm.output().is_empty() && vm.output().chars().all(|c| printable(c as u16)) =>
+            Some((address, vm.take_output())),
+        _ => None
+    }
+}
+
+/// Strings decoded by emulating every call to a printing routine whose
+/// registers are set from literals right before it, as the game does when
+/// printing obfuscated strings.
+pub fn decoded_strings(memory: &[u16]) -> Vec<Entry> {
+    let routine = match string_routine(memory) {
+        Some(routine) => routine,
+        None => return Vec::new()
+    };
+    let code = disasm::code(memory);
+    let printing = printing_routines(memory, &code, routine);
+    code.values()
+        .filter(|i| i.opcode == CALL && printing.contains(&(i.args[0] as usize)))
+        .filter_map(|call| {
+            let (address, text) = emulate(memory, setup(&code, call)?, call)?;
+            Some(Entry { address, text, reference: Some(call.address) })
+        })
+        .collect()
+}
+
+/// Length-prefixed runs of printable words.
+pub fn plain_strings(memory: &[u16]) -> Vec<Entry> {
+    let mut strings = Vec::new();
+    let mut address = 0;
+    while address < memory.len() {
+        let len = memory[address] as usize;
+        let text = memory.get(address + 1..address + 1 + len).unwrap_or(&[]);
+        if len >= MIN_PLAIN && text.len() == len && text.iter().copied().all(printable) {
+            strings.push(Entry { address, text: text.iter().map(|&w| w as u8 as char).collect(), reference: None });
+            address += len + 1;
+        } else {
+            address += 1;
+        }
+    }
+    strings
+}
+
+/// Every string of the program by address. Most of memory is decrypted by the
+/// program as it starts, so it runs until it asks for input first; the strings
+/// still obfuscated then are decoded by emulating the calls printing them.
+pub fn strings(program: &[u16]) -> Result<Vec<Entry>, String> {
+    let mut vm = SynacorVm::new(program.to_vec());
+    match vm.run_until_input() {
+        Outcome::NeedInput => {}
+        outcome => return Err(format!("{:?} before the first prompt", outcome))
+    }
+    let mut strings: BTreeMap<usize, Entry> = BTreeMap::new();
+    for entry in plain_strings(vm.memory()).into_iter().chain(decoded_strings(vm.memory())) {
+        strings.insert(entry.address, entry);
+    }
+    Ok(strings.into_values().collect())
+}
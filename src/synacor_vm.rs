@@ -1,114 +1,162 @@
-use std::collections::{HashSet, VecDeque};
-use std::io;
-use std::iter::FromIterator;
-
-fn ackermann_3n(r7: u16, n: u32) -> u16 {
-    // (x+1)^(n+2) + (x+1)^(n+1) + (x+1)^n + ... + (x+1)^2 + x
-    let mut result = 0;
-    let mut exp = n + 2;
-    loop {
-        result = (result as u32 + (r7 + 1).wrapping_pow(exp) as u32) as u16 & LITERAL;
-        exp -= 1;
-        if exp == 1 { break; }
-    }
-    result = (result as u32 + r7 as u32) as u16 & LITERAL;
-    // println!("r7: {}, n: {}, af3n: {}", r7, n, result);
-    result
-}
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-fn find_r7() -> u16 {
-    let mut r7 = 0;
-    loop {
-        // println!("Checking {}", r7);
-        let af3x = ackermann_3n(r7, r7 as u32);
-        if ackermann_3n(r7, af3x as u32) == 6 {
-            break;
-        }
-        if r7 == LITERAL { break; }
-        r7 += 1;
-    };
-    r7
+use crate::analysis::{self, PureFunction};
+use crate::codes::CodeDetector;
+use crate::decode::{Block, BlockCache, Instr, Op};
+use crate::disasm::{self, INVALID, LITERAL};
+use crate::fnv::Fnv;
+
+#[derive(Clone, Debug)]
+pub struct SynacorVm {
+    memory: Vec<u16>,
+    registers: Vec<u16>,
+    stack: Vec<u16>,
+    ip: usize,
+    input: VecDeque<char>,
+    output: String,
+    // for each register, how many times the instruction at each address read it
+    register_reads: Option<Vec<BTreeMap<usize, u64>>>,
+    // address of the last wmem that wrote each word, when tracked
+    memory_writes: Option<HashMap<usize, usize>>,
+    // pure subroutines whose results are cached, by entry
+    pure: HashMap<usize, PureFunction>,
+    memo: HashMap<(usize, Vec<u16>), Vec<u16>>,
+    // calls to pure subroutines yet to return: (stack length inside the call, entry, inputs)
+    memo_frames: Vec<(usize, usize, Vec<u16>)>,
+    hooks: Hooks,
+    engine: Engine,
+    blocks: BlockCache,
+    steps: u64
 }
 
-struct SearchUnit {
-    path: Vec<usize>,
-    value: i32,
-    op: i32,
+/// How instructions are run. Interpreter decodes each one as it gets to it.
+/// Blocks compiles basic blocks into closures with their operands resolved and
+/// runs them one after the other, and leaves code that rewrites itself to the
+/// interpreter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Engine {
+    Interpreter,
+    Blocks
 }
 
-impl SearchUnit {
-    fn new() -> SearchUnit {
-        SearchUnit { path: vec![0], value: 22, op: -3 }
+/// Native code standing in for a guest subroutine, see SynacorVm::hook.
+pub type Hook = Arc<dyn Fn(&mut SynacorVm) + Send + Sync>;
+
+#[derive(Clone, Default)]
+struct Hooks(HashMap<usize, Hook>);
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
     }
 }
 
-fn find_route(symbols: Vec<i32>) -> Vec<usize> {
-    let mut queue = VecDeque::new();
-    queue.push_back(SearchUnit::new() );
-    let mut passed: HashSet<(usize, i32)> = HashSet::new();
-    passed.insert((0, 22));
-    loop {
-        let current_unit = queue.pop_front().unwrap();
-        let loc = *(current_unit.path.last().unwrap()) as isize;
-        let (x, y) = (loc % 4, loc / 4);
-        let mut neighbors: HashSet<isize> = HashSet::from_iter(
-            vec![loc-1, loc+1, loc-4, loc+4].into_iter());
-        if x == 0 { neighbors.remove(&(loc-1)); }
-        else if x == 3 { neighbors.remove(&(loc+1)); }
-        if y == 0 { neighbors.remove(&(loc-4)); }
-        else if y == 3 { neighbors.remove(&(loc+4)); }
-        let next: Vec<_> = neighbors.into_iter().map(|n| {
-            let op = if symbols[n as usize] > -3 && symbols[n as usize] <= 0 { symbols[n as usize] } else { -3 };
-            let value =
-                if n == 0 { 22 }
-                else if symbols[n as usize] <= 0 { current_unit.value }
-                else {
-                    match current_unit.op {
-                        0 => current_unit.value * symbols[n as usize],
-                        -1 => current_unit.value + symbols[n as usize],
-                        _ => current_unit.value - symbols[n as usize]
-                    }
-                };
-            let mut path = current_unit.path.clone();
-            path.push(n as usize);
-            SearchUnit { path, value, op }
-        }).filter(|su| {
-            let loc = *(su.path.last().unwrap());
-            !passed.contains(&(loc, su.value)) && (loc != 15 || (loc == 15 && su.value == 30))
-        }).collect();
-
-        if let Some(su) = next.iter()
-            .find(|&su| *su.path.last().unwrap() == 15 && su.value == 30) {
-            break su.path.clone();
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Halted,          // halt, or ret with an empty stack
+    OutOfMemory,     // ip went past the end of memory
+    TraceLimit,      // stopped after tracing 1000 instructions
+    NeedInput,       // waiting at an `in` with nothing left to read
+    BudgetExhausted, // ran out of instructions or time, can be resumed
+    Cancelled,       // stopped through a CancelToken, can be resumed
+    Fault(Fault)     // the instruction at ip can't be executed
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    InvalidOperand(u16),       // neither a literal nor a register
+    NotARegister(u16),         // written to, but not a register
+    InvalidValue(u16),         // rmem read a word that isn't a 15-bit number
+    InvalidInput(char),        // too large for a register
+    AddressOutOfRange(usize),  // rmem, wmem or the return address of a call
+    EmptyStack,                // pop with nothing on the stack
+    DivisionByZero,
+    UnknownOpcode(u16)         // a word above 21 where an instruction should be
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::InvalidOperand(x) => write!(f, "invalid operand {}", x),
+            Fault::NotARegister(x) => write!(f, "{} is not a register", x),
+            Fault::InvalidValue(x) => write!(f, "invalid value {} in memory", x),
+            Fault::InvalidInput(ch) => write!(f, "invalid input {:?}", ch),
+            Fault::AddressOutOfRange(address) => write!(f, "address {} out of range", address),
+            Fault::EmptyStack => write!(f, "pop from an empty stack"),
+            Fault::DivisionByZero => write!(f, "division by zero"),
+            Fault::UnknownOpcode(opcode) => write!(f, "unknown opcode {}", opcode)
         }
+    }
+}
 
-        next.into_iter().for_each(|su| {
-            passed.insert((*su.path.last().unwrap(), su.value));
-            queue.push_back(su);
-        });
+impl From<Fault> for Outcome {
+    fn from(fault: Fault) -> Outcome {
+        Outcome::Fault(fault)
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct SynacorVm {
-    memory: Vec<u16>,
-    registers: Vec<u16>,
-    stack: Vec<u16>,
-    ip: usize
+/// Lets another thread stop a running VM.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits for one call to SynacorVm::run_budgeted. The deadline and the token
+/// are only looked at every few thousand instructions.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    pub instructions: Option<u64>,
+    pub deadline: Option<Instant>,
+    pub cancel: Option<CancelToken>
 }
 
-const LITERAL: u16 = 32767;
-const INVALID: u16 = 32776;
+impl Budget {
+    pub fn instructions(instructions: u64) -> Budget {
+        Budget { instructions: Some(instructions), ..Budget::default() }
+    }
 
-fn show_reg(x: u16) -> String {
-    assert!(x > LITERAL && x < INVALID);
-    format!("r{}", x - LITERAL - 1)
+    pub fn timeout(timeout: Duration) -> Budget {
+        Budget { deadline: Some(Instant::now() + timeout), ..Budget::default() }
+    }
 }
 
-fn show_val(x: u16) -> String {
-    assert!(x < INVALID);
-    if x <= LITERAL { x.to_string() }
-    else { show_reg(x) }
+const BUDGET_CHECK_INTERVAL: u64 = 4096;
+
+/// More than enough for the game to answer any one command.
+pub const COMMAND_BUDGET: u64 = 10_000_000;
+
+// accepted by the teleporter's confirmation, see teleporter::find_r7
+pub const TELEPORTER_R7: u16 = 25734;
+
+// an operand of a compiled instruction, see SynacorVm::compile
+#[derive(Clone, Copy)]
+enum Operand {
+    Literal(u16),
+    Register(usize)
+}
+
+fn operand(x: u16) -> Option<Operand> {
+    if x <= LITERAL { Some(Operand::Literal(x)) }
+    else { disasm::register(x).map(Operand::Register) }
 }
 
 impl SynacorVm {
@@ -117,240 +165,623 @@ impl SynacorVm {
             memory: program,
             registers: vec![0; 8],
             stack: Vec::new(),
-            ip: 0
+            ip: 0,
+            input: VecDeque::new(),
+            output: String::new(),
+            register_reads: None,
+            memory_writes: None,
+            pure: HashMap::new(),
+            memo: HashMap::new(),
+            memo_frames: Vec::new(),
+            hooks: Hooks::default(),
+            engine: Engine::Interpreter,
+            blocks: BlockCache::default(),
+            steps: 0
         }
     }
 
-    fn val(&self, x: u16) -> u16 {
-        assert!(x < INVALID);
-        if x <= LITERAL { x }
-        else { self.registers[(x - LITERAL) as usize - 1] }
+    fn val(&self, x: u16) -> Result<u16, Fault> {
+        if x <= LITERAL { Ok(x) }
+        else if x < INVALID { Ok(self.registers[(x - LITERAL) as usize - 1]) }
+        else { Err(Fault::InvalidOperand(x)) }
     }
 
-    fn set_reg(&mut self, x: u16, val: u16) {
-        assert!(x > LITERAL && x < INVALID);
+    fn set_reg(&mut self, x: u16, val: u16) -> Result<(), Fault> {
+        if x <= LITERAL || x >= INVALID { return Err(Fault::NotARegister(x)); }
         self.registers[(x - LITERAL) as usize - 1] = val;
+        Ok(())
     }
 
-    pub fn run(&mut self, prepared: &str, second_prepared: &str) -> u32 {
-        let mut in_buffer: VecDeque<char> = VecDeque::from(prepared.chars().collect::<Vec<_>>());
-        let mut out_buffer = String::new();
-        let mut extracting = false;
-        let mut count = 0;
-        let r7 = 25734; // find_r7();
+    fn address(&self, address: u16) -> Result<usize, Fault> {
+        let address = self.val(address)? as usize;
+        if address < self.memory.len() { Ok(address) } else { Err(Fault::AddressOutOfRange(address)) }
+    }
+
+    pub fn register(&self, index: usize) -> u16 {
+        self.registers[index]
+    }
+
+    pub fn set_register(&mut self, index: usize, val: u16) {
+        self.registers[index] = val;
+    }
+
+    pub fn memory(&self) -> &[u16] {
+        &self.memory
+    }
+
+    /// Hash of the words in `regions` of memory, so states can be compared by
+    /// the parts that matter, e.g. where the game keeps the current room.
+    /// Words past the end of memory and inverted ranges hash as empty.
+    pub fn hash_regions(&self, regions: &[Range<usize>]) -> u64 {
+        let mut hasher = Fnv::new();
+        for region in regions.iter() {
+            let end = region.end.min(self.memory.len());
+            hasher.words(&self.memory[region.start.min(end)..end]);
+        }
+        hasher.finish()
+    }
+
+    /// Starts recording which instructions read which registers.
+    pub fn track_register_reads(&mut self) {
+        self.register_reads = Some(vec![BTreeMap::new(); 8]);
+    }
+
+    pub fn register_reads(&self) -> Option<&[BTreeMap<usize, u64>]> {
+        self.register_reads.as_deref()
+    }
+
+    /// Starts recording which instruction last wrote each word of memory.
+    pub fn track_memory_writes(&mut self) {
+        self.memory_writes = Some(HashMap::new());
+    }
+
+    pub fn memory_writes(&self) -> Option<&HashMap<usize, usize>> {
+        self.memory_writes.as_ref()
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+        let size = self.memory.len();
+        self.blocks = if engine == Engine::Blocks { BlockCache::new(size) } else { BlockCache::default() };
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    /// Number of instructions executed so far, hooks not included.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn jump(&mut self, address: usize) {
+        self.ip = address;
+    }
+
+    /// Whenever execution reaches `address`, runs `hook` instead and then returns
+    /// like the guest subroutine starting there would, with a `ret`.
+    pub fn hook(&mut self, address: usize, hook: impl Fn(&mut SynacorVm) + Send + Sync + 'static) {
+        self.hooks.0.insert(address, Arc::new(hook));
+        self.blocks.forget(address);
+    }
+
+    /// Caches the results of a pure subroutine from now on.
+    pub fn memoize(&mut self, function: PureFunction) {
+        self.pure.insert(function.entry, function);
+    }
+
+    /// Caches the results of the subroutine at `entry` if it turns out to be pure.
+    pub fn memoize_at(&mut self, entry: usize) -> bool {
+        match analysis::pure_function(&self.memory, entry, &self.pure) {
+            Some(function) => { self.memoize(function); true }
+            None => false
+        }
+    }
+
+    /// Caches the results of every pure subroutine found in memory.
+    pub fn memoize_pure_functions(&mut self) -> usize {
+        let pure = analysis::pure_functions(&self.memory);
+        let count = pure.len();
+        self.pure.extend(pure);
+        count
+    }
+
+    // Serves a call to a pure subroutine from the cache, or remembers to fill
+    // the cache when it returns. True when ip has already moved past the call.
+    fn call_memoized(&mut self, target: usize) -> bool {
+        let function = match self.pure.get(&target) {
+            Some(function) => function,
+            None => return false
+        };
+        let inputs: Vec<u16> = function.inputs.iter().map(|&r| self.registers[r]).collect();
+        if let Some(outputs) = self.memo.get(&(target, inputs.clone())) {
+            for (&r, &val) in function.outputs.iter().zip(outputs.iter()) {
+                self.registers[r] = val;
+            }
+            self.ip += 2;
+            return true;
+        }
+        self.memo_frames.push((self.stack.len() + 1, target, inputs));
+        false
+    }
+
+    // After a `ret` from a stack of length `depth`, caches the result of the pure
+    // subroutine it returned from, if any.
+    fn return_memoized(&mut self, depth: usize) {
+        // frames left some other way than through their `ret`
+        while self.memo_frames.last().is_some_and(|&(frame_depth, _, _)| frame_depth > depth) {
+            self.memo_frames.pop();
+        }
+        if self.memo_frames.last().is_some_and(|&(frame_depth, _, _)| frame_depth == depth) {
+            let (_, entry, inputs) = self.memo_frames.pop().unwrap();
+            let outputs = self.pure[&entry].outputs.iter().map(|&r| self.registers[r]).collect();
+            self.memo.insert((entry, inputs), outputs);
+        }
+    }
+
+    pub fn patch(&mut self, address: usize, words: &[u16]) {
+        self.memory[address..address + words.len()].copy_from_slice(words);
+        self.code_written(address, words.len());
+    }
+
+    pub fn feed(&mut self, input: &str) {
+        self.input.extend(input.chars());
+    }
+
+    /// Output not taken yet.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn take_output(&mut self) -> String {
+        mem::take(&mut self.output)
+    }
+
+    /// Runs the walkthrough: `prepared` first, then the teleporter with r7 set,
+    /// then `second_prepared`, then whatever comes from stdin.
+    pub fn run(&mut self, prepared: &str, second_prepared: &str) -> Outcome {
+        let stdin = io::stdin();
+        self.run_io(prepared, second_prepared, &Budget::default(), stdin.lock(), io::stdout()).unwrap()
+    }
+
+    /// Like run, reading commands from `input` once the scripts are done and
+    /// writing the game to `output`. Every command gets its own `budget`.
+    pub fn run_io<R: BufRead, W: Write>(&mut self, prepared: &str, second_prepared: &str, budget: &Budget,
+                                        mut input: R, mut output: W) -> io::Result<Outcome> {
+        let mut script = script_lines(prepared);
+        let mut detector = CodeDetector::new();
+        let r7 = TELEPORTER_R7;
         let mut stop = 0;
 
         let result = loop {
-            let mem_len = self.memory.len();
-            if self.ip >= mem_len {
-                break 1;
+            let outcome = self.run_budgeted(budget);
+            let text = self.take_output();
+            write!(output, "{}", text)?;
+            detector.scan(&text);
+            if outcome != Outcome::NeedInput { break outcome; }
+
+            if script.is_empty() {
+                if stop == 0 {
+                    self.registers[7] = r7;
+                    writeln!(output, "r7: {}", r7)?;
+                    script = script_lines("use teleporter\n");
+                    stop += 1;
+                } else if stop == 1 {
+                    script = script_lines(second_prepared);
+                    stop += 1;
+                } else {
+                    let mut temp = String::new();
+                    if input.read_line(&mut temp)? == 0 { break outcome; }
+                    script.push_back(temp);
+                }
+            }
+            let command = script.pop_front().unwrap();
+            detector.command(&command);
+            self.feed(&command);
+        };
+        writeln!(output)?;
+        writeln!(output, "result {:?}", result)?;
+        for code in detector.codes() {
+            writeln!(output, "code: {}", code)?;
+        }
+        Ok(result)
+    }
+
+    /// Runs until the program stops or asks for input that hasn't been fed yet.
+    /// In the latter case ip stays on the `in`, so feeding more input and calling
+    /// this again resumes the program.
+    pub fn run_until_input(&mut self) -> Outcome {
+        self.run_budgeted(&Budget::default())
+    }
+
+    /// Runs the next instruction, or with Engine::Blocks the whole block starting
+    /// at ip if there is one, the way run_budgeted goes through it.
+    pub fn step(&mut self) -> Outcome {
+        let mut len = 1;
+        if self.engine == Engine::Blocks && self.register_reads.is_none() && self.ip < self.memory.len()
+            && !self.hooks.0.contains_key(&self.ip) {
+            let hooks = &self.hooks.0;
+            if let Some(block) = self.blocks.get(&self.memory, self.ip, |a| hooks.contains_key(&a), SynacorVm::compile) {
+                len = block.ops.len();
             }
+        }
+        self.run_budgeted(&Budget::instructions(len as u64))
+    }
+
+    /// Like run_until_input, but also stops before going over the budget. Calling
+    /// it again after BudgetExhausted or Cancelled carries on where it stopped.
+    pub fn run_budgeted(&mut self, budget: &Budget) -> Outcome {
+        // set by uncommenting one of the triggers below, which blocks run past,
+        // so trace with Engine::Interpreter
+        #[allow(unused_mut)]
+        let mut extracting = false;
+        let mut count = 0;
+        let mut executed: u64 = 0;
+        let mut next_check = 0;
 
-            // skip confirmation process
-            if self.ip == 5489 {
-                self.registers[0] = 6;
-                self.ip = 5491;
+        loop {
+            if budget.instructions.is_some_and(|limit| executed >= limit) {
+                break Outcome::BudgetExhausted;
             }
-            // after confirmation process
-            // if self.ip == 5491 {
-            //     extracting = true;
-            // }
-            let a = if self.ip + 1 < mem_len { self.memory[self.ip + 1] } else { 0 };
-            let b = if self.ip + 2 < mem_len { self.memory[self.ip + 2] } else { 0 };
-            let c = if self.ip + 3 < mem_len { self.memory[self.ip + 3] } else { 0 };
-            match self.memory[self.ip] {
-                0 => break 0, // halt
-                1 => { // set a b
-                    self.set_reg(a, self.val(b));
-                    if extracting {
-                        println!("{}: set {} {}", self.ip, show_reg(a), show_val(b));
-                    }
-                    self.ip += 3;
+            if executed >= next_check {
+                next_check = executed + BUDGET_CHECK_INTERVAL;
+                if budget.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break Outcome::BudgetExhausted;
                 }
-                2 => { // push a
-                    self.stack.push(self.val(a));
-                    if extracting {
-                        println!("{}: push {}", self.ip, show_val(a));
-                    }
-                    self.ip += 2;
+                if budget.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                    break Outcome::Cancelled;
                 }
-                3 => { // pop a
-                    assert!(!self.stack.is_empty());
-                    let val = self.stack.pop().unwrap();
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: pop {}", self.ip, show_reg(a));
-                    }
-                    self.ip += 2;
-                }
-                4 => { // eq a b c
-                    let val = if self.val(b) == self.val(c) { 1 } else { 0 };
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: eq {} {} {}", self.ip, show_reg(a), show_val(b), show_val(c));
-                    }
-                    self.ip += 4;
-                }
-                5 => { // gt a b c
-                    let val = if self.val(b) > self.val(c) { 1 } else { 0 };
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: gt {} {} {}", self.ip, show_reg(a), show_val(b), show_val(c));
-                    }
-                    self.ip += 4;
-                }
-                6 => { // jmp a
-                    if extracting {
-                        println!("{}: jmp {}", self.ip, show_val(a));
-                    }
-                    self.ip = self.val(a) as usize;
-                }
-                7 => { // jt a b
-                    if extracting {
-                        println!("{}: jt {} {}", self.ip, show_val(a), show_val(b));
-                    }
-                    if self.val(a) != 0 { self.ip = self.val(b) as usize; }
-                    else { self.ip += 3; }
-                }
-                8 => { // jf a b
-                    if extracting {
-                        println!("{}: jf {} {}", self.ip, show_val(a), show_val(b));
-                    }
-                    if self.val(a) == 0 { self.ip = self.val(b) as usize; }
-                    else { self.ip += 3; }
-                }
-                9 => { // add a b c
-                    let val = (self.val(b) + self.val(c)) & LITERAL;
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: add {} {} {}", self.ip, show_reg(a), show_val(b), show_val(c));
+            }
+            executed += 1;
+
+            if self.ip >= self.memory.len() {
+                break Outcome::OutOfMemory;
+            }
+
+            if !self.hooks.0.is_empty() {
+                if let Some(hook) = self.hooks.0.get(&self.ip).cloned() {
+                    hook(self);
+                    let depth = self.stack.len();
+                    match self.stack.pop() {
+                        Some(address) => self.ip = address as usize,
+                        None => break Outcome::Halted
                     }
-                    self.ip += 4;
-                }
-                10 => { // mult a b c
-                    let val = ((self.val(b) as u32 * self.val(c) as u32) & LITERAL as u32) as u16;
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: mult {} {} {}", self.ip, show_reg(a), show_val(b), show_val(c));
+                    if !self.memo_frames.is_empty() {
+                        self.return_memoized(depth);
                     }
-                    self.ip += 4;
+                    continue;
                 }
-                11 => { // mod a b c
-                    let val = self.val(b) % self.val(c);
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: mod {} {} {}", self.ip, show_reg(a), show_val(b), show_val(c));
+            }
+
+            if self.engine == Engine::Blocks && self.register_reads.is_none() && !extracting {
+                // up to the next check of the deadline
+                let room = budget.instructions.map_or(u64::MAX, |limit| limit - executed + 1)
+                    .min(next_check - executed + 1);
+                let (ran, outcome) = self.run_blocks(room);
+                if ran > 0 {
+                    executed += ran - 1;
+                    match outcome {
+                        Some(outcome) => break outcome,
+                        None => continue
                     }
-                    self.ip += 4;
                 }
-                12 => { // and a b c
-                    let val = self.val(b) & self.val(c);
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: and {} {} {}", self.ip, show_reg(a), show_val(b), show_val(c));
+            }
+
+            if let Some(reads) = self.register_reads.as_mut() {
+                if let Some(instruction) = disasm::decode(&self.memory, self.ip) {
+                    for (r, reads) in reads.iter_mut().enumerate() {
+                        if disasm::reads(&instruction, r) {
+                            *reads.entry(self.ip).or_insert(0) += 1;
+                        }
                     }
-                    self.ip += 4;
                 }
-                13 => { // or a b c
-                    let val = self.val(b) | self.val(c);
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: or {} {} {}", self.ip, show_reg(a), show_val(b), show_val(c));
-                    }
-                    self.ip += 4;
+            }
+
+            // after confirmation process
+            // if self.ip == 5491 {
+            //     extracting = true;
+            // }
+            if extracting {
+                match disasm::decode(&self.memory, self.ip) {
+                    Some(instruction) => println!("{}", instruction),
+                    None => println!("{}: noop {}", self.ip, self.memory[self.ip])
                 }
-                14 => { // not a b
-                    let val = !self.val(b) & LITERAL;
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: not {} {}", self.ip, show_reg(a), show_val(b));
+            }
+            let instr = Instr::decode(&self.memory, self.ip);
+            if let Err(outcome) = self.execute(instr) {
+                break outcome;
+            }
+            self.steps += 1;
+            // confirmation process
+            // if self.output.ends_with("1 billion years.\"") {
+            //     extracting = true;
+            // }
+            if extracting {
+                count += 1;
+                if count >= 1000 { break Outcome::TraceLimit; }
+            }
+        }
+    }
+
+    // Runs compiled blocks one after the other, for at most `room` instructions,
+    // until one stops the program or ip gets to a hook or to code that is not
+    // compiled. Returns how many instructions it went through.
+    fn run_blocks(&mut self, room: u64) -> (u64, Option<Outcome>) {
+        // nothing in a block writes memory, so the cache can't change under it
+        let mut blocks = mem::take(&mut self.blocks);
+        let mut ran = 0;
+        let outcome = loop {
+            if self.ip >= self.memory.len() || (!self.hooks.0.is_empty() && self.hooks.0.contains_key(&self.ip)) {
+                break None;
+            }
+            let hooks = &self.hooks.0;
+            let block = match blocks.get(&self.memory, self.ip, |a| hooks.contains_key(&a), SynacorVm::compile) {
+                Some(block) if block.ops.len() as u64 <= room - ran => block,
+                _ => break None
+            };
+            let (went, outcome) = self.execute_block(block);
+            ran += went;
+            if outcome.is_some() { break outcome; }
+        };
+        self.blocks = blocks;
+        (ran, outcome)
+    }
+
+    // Calls the closures of a block until one stops the program, returning how
+    // many it went through.
+    fn execute_block(&mut self, block: &Block) -> (u64, Option<Outcome>) {
+        for (i, op) in block.ops.iter().enumerate() {
+            if let Err(outcome) = op(self) {
+                self.steps += i as u64;
+                return (i as u64 + 1, Some(outcome));
+            }
+        }
+        self.steps += block.ops.len() as u64;
+        if !block.jumps {
+            self.ip = block.end;
+        }
+        (block.ops.len() as u64, None)
+    }
+
+    fn execute_at(&mut self, address: usize, instr: Instr) -> Result<(), Outcome> {
+        self.ip = address;
+        self.execute(instr)
+    }
+
+    fn read(&self, operand: Operand) -> u16 {
+        match operand {
+            Operand::Literal(x) => x,
+            Operand::Register(r) => self.registers[r]
+        }
+    }
+
+    // Compiles the instruction at `address` for a block, its operands resolved
+    // once and for all. Input, halts and whatever faults go through execute
+    // with ip on the instruction.
+    fn compile(instr: Instr, address: usize) -> Op {
+        fn interpret(instr: Instr, address: usize) -> Op {
+            Box::new(move |vm: &mut SynacorVm| vm.execute_at(address, instr))
+        }
+        fn unary(instr: Instr, address: usize, a: u16, b: u16, f: impl Fn(u16) -> u16 + Send + Sync + 'static) -> Op {
+            match (disasm::register(a), operand(b)) {
+                (Some(a), Some(b)) => Box::new(move |vm: &mut SynacorVm| {
+                    vm.registers[a] = f(vm.read(b));
+                    Ok(())
+                }),
+                _ => interpret(instr, address)
+            }
+        }
+        fn binary(instr: Instr, address: usize, (a, b, c): (u16, u16, u16),
+                  f: impl Fn(u16, u16) -> u16 + Send + Sync + 'static) -> Op {
+            match (disasm::register(a), operand(b), operand(c)) {
+                (Some(a), Some(b), Some(c)) => Box::new(move |vm: &mut SynacorVm| {
+                    vm.registers[a] = f(vm.read(b), vm.read(c));
+                    Ok(())
+                }),
+                _ => interpret(instr, address)
+            }
+        }
+
+        match instr {
+            Instr::Set(a, b) => unary(instr, address, a, b, |x| x),
+            Instr::Push(a) => match operand(a) {
+                Some(a) => Box::new(move |vm: &mut SynacorVm| {
+                    let val = vm.read(a);
+                    vm.stack.push(val);
+                    Ok(())
+                }),
+                None => interpret(instr, address)
+            },
+            Instr::Pop(a) => match disasm::register(a) {
+                Some(a) => Box::new(move |vm: &mut SynacorVm| match vm.stack.pop() {
+                    Some(val) => {
+                        vm.registers[a] = val;
+                        Ok(())
                     }
-                    self.ip += 3;
-                }
-                15 => { // rmem a b
-                    let val = self.memory[self.val(b) as usize];
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: rmem {} {}", self.ip, show_reg(a), show_val(b));
+                    None => vm.execute_at(address, instr)
+                }),
+                None => interpret(instr, address)
+            },
+            Instr::Eq(a, b, c) => binary(instr, address, (a, b, c), |x, y| (x == y) as u16),
+            Instr::Gt(a, b, c) => binary(instr, address, (a, b, c), |x, y| (x > y) as u16),
+            Instr::Add(a, b, c) => binary(instr, address, (a, b, c), |x, y| (x + y) & LITERAL),
+            Instr::Mult(a, b, c) => binary(instr, address, (a, b, c), |x, y| ((x as u32 * y as u32) & LITERAL as u32) as u16),
+            Instr::Mod(a, b, c) => match (disasm::register(a), operand(b), operand(c)) {
+                (Some(a), Some(b), Some(c)) => Box::new(move |vm: &mut SynacorVm| match vm.read(b).checked_rem(vm.read(c)) {
+                    Some(val) => {
+                        vm.registers[a] = val;
+                        Ok(())
                     }
-                    self.ip += 3;
-                }
-                16 => { // wmem a b
-                    let loc = self.val(a);
-                    self.memory[loc as usize] = self.val(b);
-                    if extracting {
-                        println!("{}: wmem {} {}", self.ip, show_val(a), show_val(b));
+                    None => vm.execute_at(address, instr)
+                }),
+                _ => interpret(instr, address)
+            },
+            Instr::And(a, b, c) => binary(instr, address, (a, b, c), |x, y| x & y),
+            Instr::Or(a, b, c) => binary(instr, address, (a, b, c), |x, y| x | y),
+            Instr::Not(a, b) => unary(instr, address, a, b, |x| !x & LITERAL),
+            Instr::Rmem(a, b) => match (disasm::register(a), operand(b)) {
+                (Some(a), Some(b)) => Box::new(move |vm: &mut SynacorVm| match vm.memory.get(vm.read(b) as usize) {
+                    Some(&val) if val <= LITERAL => {
+                        vm.registers[a] = val;
+                        Ok(())
                     }
-                    self.ip += 3;
+                    _ => vm.execute_at(address, instr)
+                }),
+                _ => interpret(instr, address)
+            },
+            Instr::Out(a) => match operand(a) {
+                Some(a) => Box::new(move |vm: &mut SynacorVm| {
+                    let val = vm.read(a);
+                    vm.output.push((val as u8) as char);
+                    Ok(())
+                }),
+                None => interpret(instr, address)
+            },
+            Instr::Noop => Box::new(|_: &mut SynacorVm| Ok(())),
+            Instr::Jmp(a) => match operand(a) {
+                Some(a) => Box::new(move |vm: &mut SynacorVm| {
+                    vm.ip = vm.read(a) as usize;
+                    Ok(())
+                }),
+                None => interpret(instr, address)
+            },
+            Instr::Jt(a, b) | Instr::Jf(a, b) => match (operand(a), operand(b)) {
+                (Some(a), Some(b)) => {
+                    let when = matches!(instr, Instr::Jt(..));
+                    Box::new(move |vm: &mut SynacorVm| {
+                        vm.ip = if (vm.read(a) != 0) == when { vm.read(b) as usize } else { address + 3 };
+                        Ok(())
+                    })
                 }
-                17 => { // call a
-                    self.stack.push(self.ip as u16 + 2);
-                    if extracting {
-                        println!("{}: call {}", self.ip, show_val(a));
-                    }
-                    self.ip = self.val(a) as usize;
+                _ => interpret(instr, address)
+            },
+            // memoized subroutines are called and returned from by execute
+            Instr::Call(a) => match operand(a) {
+                Some(a) if address + 2 <= LITERAL as usize => Box::new(move |vm: &mut SynacorVm| {
+                    if !vm.pure.is_empty() { return vm.execute_at(address, instr); }
+                    vm.stack.push(address as u16 + 2);
+                    vm.ip = vm.read(a) as usize;
+                    Ok(())
+                }),
+                _ => interpret(instr, address)
+            },
+            Instr::Ret => Box::new(move |vm: &mut SynacorVm| {
+                if !vm.memo_frames.is_empty() || vm.stack.is_empty() { return vm.execute_at(address, instr); }
+                vm.ip = vm.stack.pop().unwrap() as usize;
+                Ok(())
+            }),
+            _ => interpret(instr, address)
+        }
+    }
+
+    fn code_written(&mut self, address: usize, len: usize) {
+        self.blocks.invalidate(address, len);
+    }
+
+    // Executes one instruction, or returns the outcome if it stops the program.
+    fn execute(&mut self, instr: Instr) -> Result<(), Outcome> {
+        match instr {
+            Instr::Halt => return Err(Outcome::Halted),
+            Instr::Set(a, b) => self.set_reg(a, self.val(b)?)?,
+            Instr::Push(a) => self.stack.push(self.val(a)?),
+            Instr::Pop(a) => {
+                let val = self.stack.pop().ok_or(Fault::EmptyStack)?;
+                if let Err(fault) = self.set_reg(a, val) {
+                    self.stack.push(val);
+                    return Err(fault.into());
                 }
-                18 => { // ret
-                    if extracting {
-                        println!("{}: ret", self.ip);
-                    }
-                    if self.stack.is_empty() { break 0; }
-                    else { self.ip = self.stack.pop().unwrap() as usize; }
+            }
+            Instr::Eq(a, b, c) => {
+                let val = if self.val(b)? == self.val(c)? { 1 } else { 0 };
+                self.set_reg(a, val)?;
+            }
+            Instr::Gt(a, b, c) => {
+                let val = if self.val(b)? > self.val(c)? { 1 } else { 0 };
+                self.set_reg(a, val)?;
+            }
+            Instr::Jmp(a) => {
+                self.ip = self.val(a)? as usize;
+                return Ok(());
+            }
+            Instr::Jt(a, b) => if self.val(a)? != 0 {
+                self.ip = self.val(b)? as usize;
+                return Ok(());
+            }
+            Instr::Jf(a, b) => if self.val(a)? == 0 {
+                self.ip = self.val(b)? as usize;
+                return Ok(());
+            }
+            Instr::Add(a, b, c) => self.set_reg(a, (self.val(b)? + self.val(c)?) & LITERAL)?,
+            Instr::Mult(a, b, c) => {
+                let val = ((self.val(b)? as u32 * self.val(c)? as u32) & LITERAL as u32) as u16;
+                self.set_reg(a, val)?;
+            }
+            Instr::Mod(a, b, c) => {
+                let val = self.val(b)?.checked_rem(self.val(c)?).ok_or(Fault::DivisionByZero)?;
+                self.set_reg(a, val)?;
+            }
+            Instr::And(a, b, c) => self.set_reg(a, self.val(b)? & self.val(c)?)?,
+            Instr::Or(a, b, c) => self.set_reg(a, self.val(b)? | self.val(c)?)?,
+            Instr::Not(a, b) => self.set_reg(a, !self.val(b)? & LITERAL)?,
+            Instr::Rmem(a, b) => {
+                let val = self.memory[self.address(b)?];
+                if val > LITERAL { return Err(Fault::InvalidValue(val).into()); }
+                self.set_reg(a, val)?;
+            }
+            Instr::Wmem(a, b) => {
+                let loc = self.address(a)?;
+                self.memory[loc] = self.val(b)?;
+                self.code_written(loc, 1);
+                if let Some(writes) = self.memory_writes.as_mut() {
+                    writes.insert(loc, self.ip);
                 }
-                19 => { // out a
-                    out_buffer.push((self.val(a) as u8) as char);
-                    // confirmation process
-                    // if out_buffer.ends_with("1 billion years.\"") {
-                    //     extracting = true;
-                    // }
-                    // print!("{}", (self.val(a) as u8) as char);
-                    if extracting {
-                        println!("{}: out {}", self.ip, (self.val(a) as u8) as char);
-                    }
-                    self.ip += 2;
+            }
+            Instr::Call(a) => {
+                let target = self.val(a)? as usize;
+                let next = self.ip + 2;
+                if next > LITERAL as usize { return Err(Fault::AddressOutOfRange(next).into()); }
+                if !self.call_memoized(target) {
+                    self.stack.push(next as u16);
+                    self.ip = target;
                 }
-                20 => { // in a
-                    print!("{}", out_buffer);
-
-                    if in_buffer.is_empty() {
-                        if stop == 0 {
-                            self.registers[7] = r7;
-                            println!("r7: {}", r7);
-                            in_buffer = "use teleporter\n".chars().collect();
-                            stop += 1;
-                        } else if stop == 1 {
-                            let symbols = vec![22, -2, 9, 0, -1, 4, -2, 18, 4, 0, 11, 0, 0, 8, -2, 1];
-                            println!("route: {:?}", find_route(symbols));
-                            in_buffer = second_prepared.chars().collect();
-                            stop += 1;
-                        } else {
-                            let mut temp = String::new();
-                            io::stdin().read_line(&mut temp).unwrap();
-                            in_buffer = temp.chars().collect();
-                        }
-                    }
-                    let val = in_buffer.pop_front().unwrap() as u16;
-                    self.set_reg(a, val);
-                    if extracting {
-                        println!("{}: in {}", self.ip, show_reg(a));
-                    }
-                    self.ip += 2;
-                    out_buffer = String::new();
+                return Ok(());
+            }
+            Instr::Ret => {
+                let depth = self.stack.len();
+                match self.stack.pop() {
+                    Some(address) => self.ip = address as usize,
+                    None => return Err(Outcome::Halted)
                 }
-                n => {
-                    if extracting {
-                        println!("{}: noop {}", self.ip, n);
-                    }
-                    self.ip += 1;
+                if !self.memo_frames.is_empty() {
+                    self.return_memoized(depth);
                 }
+                return Ok(());
             }
-            if extracting {
-                count += 1;
-                if count >= 1000 { break 2; }
+            Instr::Out(a) => {
+                self.output.push((self.val(a)? as u8) as char);
             }
-        };
-        println!("{}", out_buffer);
-        println!("result {}", result);
-        result
+            Instr::In(a) => {
+                let ch = *self.input.front().ok_or(Outcome::NeedInput)?;
+                if ch as u32 > LITERAL as u32 { return Err(Fault::InvalidInput(ch).into()); }
+                self.set_reg(a, ch as u16)?;
+                self.input.pop_front();
+            }
+            Instr::Noop => {}
+            Instr::Unknown(opcode) => Err(Fault::UnknownOpcode(opcode))?
+        }
+        self.ip += instr.size();
+        Ok(())
     }
 }
+
+fn script_lines(script: &str) -> VecDeque<String> {
+    script.lines().map(|line| format!("{}\n", line)).collect()
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::mem;
+use std::thread;
+
+use crate::disasm::{self, Instruction, CALL, EQ, LITERAL, RET, SET};
+use crate::synacor_vm::{Budget, Engine, Outcome, SynacorVm, COMMAND_BUDGET, TELEPORTER_R7};
+
+const MODULO: usize = 32768;
+
+/// Closed form of af(3, n) for a given r7, see the notes in main.rs.
+pub fn ackermann_3n(r7: u16, n: u32) -> u16 {
+    // (x+1)^(n+2) + (x+1)^(n+1) + (x+1)^n + ... + (x+1)^2 + x
+    // = (x+1)^2 * (1 + (x+1) + ... + (x+1)^n) + x
+    let a = r7 as u32 + 1;
+    let powers = a.wrapping_pow(2) & LITERAL as u32;
+    ((powers * geometric(a, n + 1) + r7 as u32) & LITERAL as u32) as u16
+}
+
+// 1 + a + a^2 + ... + a^(count - 1) modulo 32768, halving the count at each step
+fn geometric(a: u32, count: u32) -> u32 {
+    let sum = match count {
+        0 => 0,
+        _ if count % 2 == 1 => 1 + a * geometric(a, count - 1),
+        _ => geometric(a, count / 2) * (1 + (a.wrapping_pow(count / 2) & LITERAL as u32))
+    };
+    sum & LITERAL as u32
+}
+
+/// The modified Ackermann function of the confirmation routine, modulo 32768:
+/// af(0, n) = n + 1
+/// af(m + 1, 0) = af(m, r7)
+/// af(m + 1, n + 1) = af(m, af(m + 1, n))
+/// Each af(m, _) is tabulated for every n from the table of af(m - 1, _), so the
+/// buffers are kept around to evaluate many r7 in a row.
+pub struct Ackermann {
+    row: Vec<u16>,
+    next: Vec<u16>
+}
+
+impl Ackermann {
+    pub fn new() -> Ackermann {
+        Ackermann { row: vec![0; MODULO], next: vec![0; MODULO] }
+    }
+
+    pub fn eval(&mut self, m: u16, n: u16, r7: u16) -> u16 {
+        for (i, v) in self.row.iter_mut().enumerate() {
+            *v = (i as u16 + 1) & LITERAL;
+        }
+        let n = n as usize & LITERAL as usize;
+        for level in 1..=m {
+            // the last table is only needed up to n
+            let len = if level == m { n + 1 } else { MODULO };
+            self.next[0] = self.row[r7 as usize];
+            for i in 1..len {
+                self.next[i] = self.row[self.next[i - 1] as usize];
+            }
+            mem::swap(&mut self.row, &mut self.next);
+        }
+        self.row[n]
+    }
+}
+
+impl Default for Ackermann {
+    fn default() -> Ackermann {
+        Ackermann::new()
+    }
+}
+
+pub fn ackermann(m: u16, n: u16, r7: u16) -> u16 {
+    Ackermann::new().eval(m, n, r7)
+}
+
+/// Where the teleporter calls the confirmation routine and checks its result:
+/// 5483: set r0 4
+/// 5486: set r1 1
+/// 5489: call 6027
+/// 5491: eq r1 r0 6
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallSite {
+    pub address: usize,
+    pub function: usize,
+    pub r0: u16,
+    pub r1: u16,
+    pub check: usize,
+    pub target: u16
+}
+
+impl fmt::Display for CallSite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: call {} with r0 = {}, r1 = {}, checked at {} against {}",
+               self.address, self.function, self.r0, self.r1, self.check, self.target)
+    }
+}
+
+// `call` followed by `eq _ r0 target`, with r0 and r1 set right before the call
+fn call_site_at(memory: &[u16], call: &Instruction) -> Option<CallSite> {
+    let check = disasm::decode(memory, call.next())?;
+    if check.opcode != EQ { return None; }
+    let target = match (check.args[1], check.args[2]) {
+        (r, t) | (t, r) if r == disasm::reg(0) => disasm::literal(t)?,
+        _ => return None
+    };
+
+    let (mut r0, mut r1) = (None, None);
+    let mut address = call.address;
+    for _ in 0..2 {
+        let set = disasm::decode(memory, address.checked_sub(3)?)?;
+        if set.opcode != SET { break; }
+        match disasm::register(set.args[0]) {
+            Some(0) if r0.is_none() => r0 = disasm::literal(set.args[1]),
+            Some(1) if r1.is_none() => r1 = disasm::literal(set.args[1]),
+            _ => break
+        }
+        address = set.address;
+    }
+
+    Some(CallSite {
+        address: call.address,
+        function: disasm::literal(call.args[0])? as usize,
+        r0: r0?,
+        r1: r1?,
+        check: check.address,
+        target
+    })
+}
+
+/// Functions calling themselves and reading r7, which nothing else should look at.
+pub fn confirmation_routines(memory: &[u16]) -> Vec<usize> {
+    let code = disasm::code(memory);
+    let entries: BTreeSet<usize> = code.values()
+        .filter(|instruction| instruction.opcode == CALL)
+        .filter_map(|instruction| disasm::literal(instruction.args[0]))
+        .map(|a| a as usize)
+        .collect();
+    entries.into_iter()
+        .filter(|&entry| {
+            let body = disasm::function_body(memory, entry);
+            body.values().any(|i| disasm::reads(i, 7))
+                && body.values().any(|i| i.opcode == CALL && i.args[0] == entry as u16)
+        })
+        .collect()
+}
+
+/// Finds the confirmation routine and the call to it whose result is checked,
+/// whatever addresses they sit at in this build.
+pub fn find_call_site(memory: &[u16]) -> Option<CallSite> {
+    confirmation_routines(memory).into_iter().find_map(|function| {
+        let body = disasm::function_body(memory, function);
+        (0..memory.len())
+            .filter_map(|address| disasm::decode(memory, address))
+            .filter(|i| i.opcode == CALL && i.args[0] == function as u16 && !body.contains_key(&i.address))
+            .find_map(|call| call_site_at(memory, &call))
+    })
+}
+
+/// Skips the confirmation by replacing the start of the routine with
+/// `set r0 <target>; ret`, so the check after the call always passes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bypass {
+    pub address: usize,
+    pub words: Vec<u16>
+}
+
+impl Bypass {
+    pub fn new(site: &CallSite) -> Bypass {
+        Bypass {
+            address: site.function,
+            words: vec![SET, disasm::reg(0), site.target, RET]
+        }
+    }
+}
+
+impl fmt::Display for Bypass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let patched: Vec<_> = self.words.iter().map(|w| w.to_string()).collect();
+        write!(f, "patch {}: {}", self.address, patched.join(","))
+    }
+}
+
+/// Every r7 for which the confirmation routine returns the expected value,
+/// searched on as many threads as there are cores. af(m, _) comes from the
+/// closed forms for m up to 4 and from the tables above that.
+pub fn find_r7(site: &CallSite) -> Vec<u16> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = MODULO.div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..MODULO).step_by(chunk)
+            .map(|from| scope.spawn(move || {
+                (from..MODULO.min(from + chunk))
+                    .map(|r7| r7 as u16)
+                    .filter(|&r7| ackermann_native(site.r0, site.r1, r7) == site.target)
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+/// af(m, n) from the closed forms of af(m, _) for m up to 3, see the notes in
+/// main.rs; af(4, n) applies af(3, _) n + 1 times. Larger m go through the tables.
+pub fn ackermann_native(m: u16, n: u16, r7: u16) -> u16 {
+    let (x, n32) = (r7 as u32, n as u32);
+    match m {
+        0 => (n + 1) & LITERAL,
+        1 => ((x + 1 + n32) & LITERAL as u32) as u16,
+        2 => (((n32 + 1) * (x + 1) + x) & LITERAL as u32) as u16,
+        3 => ackermann_3n(r7, n32),
+        4 => (0..n).fold(ackermann_3n(r7, x), |af, _| ackermann_3n(r7, af as u32)),
+        _ => ackermann(m, n, r7)
+    }
+}
+
+/// Stands in for the confirmation routine: r0 = af(r0, r1) and, as the guest
+/// always returns from af(0, r0 - 1), r1 = r0 - 1.
+pub fn native_confirmation(vm: &mut SynacorVm) {
+    let result = ackermann_native(vm.register(0), vm.register(1), vm.register(7));
+    vm.set_register(0, result);
+    vm.set_register(1, result.wrapping_sub(1) & LITERAL);
+}
+
+// r7 for which af(4, n) is compared with the tables, spread over every value
+const TABLE_R7_STEP: usize = 997;
+
+/// Runs the guest routine at `entry` and the native one on small inputs,
+/// returning the (r0, r1, r7) for which their registers differ afterwards.
+/// af(4, n), which the teleporter asks for, only finishes in the guest for the
+/// smallest r7, so it is also checked against the tables, over r7 from all
+/// over the range.
+pub fn check_native(memory: &[u16], entry: usize) -> Vec<(u16, u16, u16)> {
+    let mut mismatches = Vec::new();
+    let inputs = (0..3).flat_map(|m| (0..5).flat_map(move |n| (0..4).map(move |r7| (m, n, r7))))
+        .chain((0..3).flat_map(|n| (0..3).map(move |r7| (3, n, r7))))
+        .chain((0..5).map(|n| (4, n, 0)))
+        .chain([(4, 0, 1), (4, 0, 2)].iter().copied());
+    for (m, n, r7) in inputs {
+        let mut guest = SynacorVm::new(memory.to_vec());
+        guest.set_register(0, m);
+        guest.set_register(1, n);
+        guest.set_register(7, r7);
+        let mut native = guest.clone();
+        guest.set_engine(Engine::Blocks);
+        guest.jump(entry);
+        // the ret from the routine finds an empty stack and halts
+        if guest.run_budgeted(&Budget::instructions(COMMAND_BUDGET)) != Outcome::Halted {
+            mismatches.push((m, n, r7));
+            continue;
+        }
+        native_confirmation(&mut native);
+        if (0..8).any(|r| guest.register(r) != native.register(r)) {
+            mismatches.push((m, n, r7));
+        }
+    }
+
+    let mut af = Ackermann::new();
+    let r7s = (0..MODULO).step_by(TABLE_R7_STEP).map(|r7| r7 as u16).chain(Some(TELEPORTER_R7));
+    for r7 in r7s {
+        for n in 0..3 {
+            if ackermann_native(4, n, r7) != af.eval(4, n, r7) {
+                mismatches.push((4, n, r7));
+            }
+        }
+    }
+    mismatches
+}
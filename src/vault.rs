@@ -0,0 +1,355 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::game::{self, GameView, Room};
+use crate::synacor_vm::{Budget, Outcome, SynacorVm, COMMAND_BUDGET};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West
+}
+
+const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+impl Direction {
+    pub fn command(self) -> &'static str {
+        match self {
+            Direction::North => "north",
+            Direction::East => "east",
+            Direction::South => "south",
+            Direction::West => "west"
+        }
+    }
+
+    // (row, column) offsets, rows going from north to south
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::East => (0, 1),
+            Direction::South => (1, 0),
+            Direction::West => (0, -1)
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.command())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod
+}
+
+impl Op {
+    pub fn from_name(name: &str) -> Option<Op> {
+        match name {
+            "add" => Some(Op::Add),
+            "sub" => Some(Op::Sub),
+            "mul" => Some(Op::Mul),
+            "div" => Some(Op::Div),
+            "mod" => Some(Op::Mod),
+            _ => None
+        }
+    }
+
+    fn apply(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            Op::Add => a.checked_add(b),
+            Op::Sub => a.checked_sub(b),
+            Op::Mul => a.checked_mul(b),
+            Op::Div => a.checked_div(b),
+            Op::Mod => a.checked_rem(b)
+        }
+    }
+}
+
+/// The symbols operator cells are written with, and what they do.
+pub type Operators = HashMap<String, Op>;
+
+/// The operators on the floor of the game's vault grid.
+pub fn game_operators() -> Operators {
+    [("+", Op::Add), ("-", Op::Sub), ("*", Op::Mul)].iter()
+        .map(|&(symbol, op)| (symbol.to_string(), op))
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell {
+    Number(i64),
+    Op(Op)
+}
+
+impl Cell {
+    pub fn parse(symbol: &str, operators: &Operators) -> Option<Cell> {
+        let symbol = symbol.trim();
+        symbol.parse().ok().map(Cell::Number)
+            .or_else(|| operators.get(symbol).copied().map(Cell::Op))
+    }
+}
+
+// (cell, orb's weight, operator waiting for the next number)
+type State = (usize, i64, Option<Op>);
+
+// the orb's weight is kept in 0..32768 so that the search stays finite
+const MAX_VALUE: i64 = 32767;
+
+/// The grid of rooms in front of the vault. Rows go from north to south, so
+/// cell (0, 0) is the north-west corner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Option<Cell>>,
+    pub start: usize,
+    pub target: usize,
+    pub start_value: i64,
+    pub target_value: i64
+}
+
+impl Grid {
+    /// {
+    ///   "operators": {"+": "add", "-": "sub", "*": "mul"},
+    ///   "rows": [["*", "8", "-", "1"], ..., ["22", "-", "9", "*"]],
+    ///   "start": [3, 0], "target": [0, 3],
+    ///   "start_value": 22, "target_value": 30
+    /// }
+    /// `operators` maps the symbols of operator cells to add, sub, mul, div or
+    /// mod, and defaults to those of the game. `start` and `target` are
+    /// [row, column]. `start_value` defaults to the number in the start cell.
+    /// A `null` cell is a hole in the grid.
+    pub fn from_json(text: &str) -> Result<Grid, String> {
+        let json: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let operators = match &json["operators"] {
+            Value::Null => game_operators(),
+            Value::Object(operators) => operators.iter()
+                .map(|(symbol, name)| match name.as_str().and_then(Op::from_name) {
+                    Some(op) => Ok((symbol.clone(), op)),
+                    None => Err(format!("unknown operator {}", name))
+                })
+                .collect::<Result<_, String>>()?,
+            _ => return Err("operators is not an object".to_string())
+        };
+        let rows = json["rows"].as_array().ok_or("missing rows")?;
+        let height = rows.len();
+        let width = rows.first().and_then(Value::as_array).map_or(0, Vec::len);
+        if width == 0 { return Err("empty grid".to_string()); }
+
+        let mut cells = Vec::new();
+        for row in rows {
+            let row = row.as_array().ok_or("a row is not an array")?;
+            if row.len() != width { return Err("rows have different lengths".to_string()); }
+            for cell in row {
+                cells.push(match cell {
+                    Value::Null => None,
+                    Value::Number(n) => Some(Cell::Number(n.as_i64().ok_or("bad number")?)),
+                    Value::String(s) => Some(Cell::parse(s, &operators).ok_or(format!("unknown cell {}", s))?),
+                    _ => return Err(format!("unknown cell {}", cell))
+                });
+            }
+        }
+
+        let position = |key: &str| -> Result<usize, String> {
+            let pos = json[key].as_array().ok_or(format!("missing {}", key))?;
+            match (pos.first().and_then(Value::as_u64), pos.get(1).and_then(Value::as_u64)) {
+                (Some(row), Some(col)) if (row as usize) < height && (col as usize) < width =>
+                    Ok(row as usize * width + col as usize),
+                _ => Err(format!("bad {}", key))
+            }
+        };
+        let start = position("start")?;
+        let target = position("target")?;
+        let start_value = match (json["start_value"].as_i64(), cells[start]) {
+            (Some(value), _) => value,
+            (None, Some(Cell::Number(value))) => value,
+            _ => return Err("missing start_value".to_string())
+        };
+        let target_value = json["target_value"].as_i64().ok_or("missing target_value")?;
+
+        Ok(Grid { width, height, cells, start, target, start_value, target_value })
+    }
+
+    fn neighbor(&self, cell: usize, direction: Direction) -> Option<usize> {
+        let (dr, dc) = direction.delta();
+        let row = (cell / self.width) as isize + dr;
+        let col = (cell % self.width) as isize + dc;
+        if row < 0 || col < 0 || row >= self.height as isize || col >= self.width as isize {
+            return None;
+        }
+        let next = row as usize * self.width + col as usize;
+        self.cells[next].map(|_| next)
+    }
+
+    /// Shortest walk from the start cell to the target cell arriving with the
+    /// target value. Numbers are combined with the value using the operator of
+    /// the cell walked through right before them. The walk never comes back to
+    /// the start cell, where the orb would reset.
+    pub fn solve(&self) -> Option<Vec<Direction>> {
+        let start: State = (self.start, self.start_value, None);
+        let mut parents: HashMap<State, (State, Direction)> = HashMap::new();
+        let mut passed = HashSet::new();
+        passed.insert(start);
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+
+        while let Some(state) = queue.pop_front() {
+            let (cell, value, op) = state;
+            for &direction in DIRECTIONS.iter() {
+                let next = match self.neighbor(cell, direction) {
+                    Some(next) => next,
+                    None => continue
+                };
+                // walking back into the start cell puts the orb back on its pedestal
+                if next == self.start { continue; }
+                let next_state = match self.cells[next] {
+                    Some(Cell::Op(next_op)) => (next, value, Some(next_op)),
+                    Some(Cell::Number(n)) => match op.and_then(|op| op.apply(value, n)) {
+                        Some(v) => (next, v, None),
+                        None => continue
+                    },
+                    None => continue
+                };
+                if next_state.1 < 0 || next_state.1 > MAX_VALUE || passed.contains(&next_state) {
+                    continue;
+                }
+                parents.insert(next_state, (state, direction));
+                if next == self.target {
+                    if next_state.1 != self.target_value { continue; }
+                    let mut route = Vec::new();
+                    let mut current = next_state;
+                    while let Some(&(parent, direction)) = parents.get(&current) {
+                        route.push(direction);
+                        current = parent;
+                    }
+                    route.reverse();
+                    return Some(route);
+                }
+                passed.insert(next_state);
+                queue.push_back(next_state);
+            }
+        }
+        None
+    }
+}
+
+pub fn script(route: &[Direction]) -> String {
+    route.iter().map(|direction| format!("{}\n", direction)).collect()
+}
+
+// the text between the quotes following `marker`: "depicting the number '4'" => 4
+fn quoted_after<'a>(output: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = &output[output.find(marker)? + marker.len()..];
+    let rest = &rest[rest.find('\'')? + 1..];
+    Some(&rest[..rest.find('\'')?])
+}
+
+fn go(vm: &SynacorVm, direction: Direction) -> Option<(SynacorVm, Room)> {
+    let mut vm = vm.clone();
+    let room = game::play(&mut vm, direction.command()).ok()?.room?;
+    Some((vm, room))
+}
+
+/// The number on the orb's pedestal, if `room` is the antechamber of the vault.
+pub fn pedestal(room: &Room) -> Option<i64> {
+    quoted_after(&room.description, "the number")?.parse().ok()
+}
+
+/// Builds the grid by walking it. `vm` has to be waiting for a command in the
+/// antechamber, the room with the orb; it is left untouched.
+pub fn probe(vm: &SynacorVm) -> Result<Grid, String> {
+    let operators = game_operators();
+    let mut vm = vm.clone();
+    let view = game::play(&mut vm, "look").map_err(|outcome| format!("{:?} on look", outcome))?;
+    let start_value = view.room.as_ref().and_then(pedestal).ok_or("not in the vault antechamber")?;
+
+    let mut cells: HashMap<(isize, isize), Cell> = HashMap::new();
+    cells.insert((0, 0), Cell::Number(start_value));
+    let mut target = None;
+    let mut queue = VecDeque::new();
+    queue.push_back(((0, 0), vm));
+    while let Some(((row, col), vm)) = queue.pop_front() {
+        for &direction in DIRECTIONS.iter() {
+            let (dr, dc) = direction.delta();
+            let position = (row + dr, col + dc);
+            if cells.contains_key(&position) { continue; }
+            let (next_vm, room) = match go(&vm, direction) {
+                Some(next) => next,
+                None => continue
+            };
+            if !room.name.starts_with("Vault ") { continue; }
+            let cell = quoted_after(&room.description, "depicting")
+                .and_then(|symbol| Cell::parse(symbol, &operators))
+                .ok_or(format!("unreadable floor at {:?}", position))?;
+            cells.insert(position, cell);
+            if let Some(value) = quoted_after(&room.description, "it has a large") {
+                let value = value.parse().map_err(|_| "unreadable vault door".to_string())?;
+                target = Some((position, value));
+            }
+            queue.push_back((position, next_vm));
+        }
+    }
+
+    let (target, target_value) = target.ok_or("vault door not found")?;
+    let min_row = cells.keys().map(|p| p.0).min().unwrap();
+    let min_col = cells.keys().map(|p| p.1).min().unwrap();
+    let height = (cells.keys().map(|p| p.0).max().unwrap() - min_row + 1) as usize;
+    let width = (cells.keys().map(|p| p.1).max().unwrap() - min_col + 1) as usize;
+    let index = |(row, col): (isize, isize)| (row - min_row) as usize * width + (col - min_col) as usize;
+    let mut grid_cells = vec![None; width * height];
+    for (&position, &cell) in cells.iter() {
+        grid_cells[index(position)] = Some(cell);
+    }
+    Ok(Grid {
+        width,
+        height,
+        cells: grid_cells,
+        start: index((0, 0)),
+        target: index(target),
+        start_value,
+        target_value
+    })
+}
+
+/// Takes the orb, walks `route` and tries to enter the vault. `vm` has to be
+/// waiting in the antechamber; it is left untouched.
+pub fn verify(vm: &SynacorVm, route: &[Direction]) -> bool {
+    let mut vm = vm.clone();
+    vm.feed("take orb\n");
+    vm.feed(&script(route));
+    vm.feed("vault\n");
+    vm.run_budgeted(&Budget::instructions(COMMAND_BUDGET)) == Outcome::NeedInput
+        && GameView::parse(&vm.take_output()).room.is_some_and(|room| room.name == "Vault")
+}
+
+fn route_commands(route: &[Direction]) -> String {
+    format!("take orb\n{}vault\n", script(route))
+}
+
+/// The commands from taking the orb to entering the vault along the route
+/// through `grid`, such as one read from JSON.
+pub fn grid_commands(grid: &Grid) -> Result<String, String> {
+    let route = grid.solve().ok_or("no route through the grid")?;
+    Ok(route_commands(&route))
+}
+
+/// Probes and solves the grid from the antechamber, checks the route in a copy
+/// of the game and returns the commands from taking the orb to entering the vault.
+pub fn commands(vm: &SynacorVm) -> Result<String, String> {
+    let grid = probe(vm)?;
+    let route = grid.solve().ok_or("no route through the grid")?;
+    if !verify(vm, &route) {
+        return Err(format!("the vault door stays shut after {:?}", route));
+    }
+    Ok(route_commands(&route))
+}
@@ -0,0 +1,95 @@
+use crate::coins;
+use crate::synacor_vm::{Budget, Outcome, SynacorVm, COMMAND_BUDGET};
+use crate::vault;
+
+// from the start to the monument with every coin
+pub const TO_MONUMENT: &str = r#"doorway
+north
+north
+bridge
+continue
+down
+east
+take empty lantern
+west
+west
+passage
+ladder
+west
+south
+north
+take can
+use can
+use lantern
+west
+ladder
+darkness
+continue
+west
+west
+west
+west
+north
+take red coin
+north
+east
+take concave coin
+down
+take corroded coin
+up
+west
+west
+take blue coin
+up
+take shiny coin
+down
+east
+"#;
+pub const AFTER_COINS: &str = r#"north
+take teleporter
+use teleporter
+take business card
+"#;
+// once teleported to the beach
+pub const TO_ANTECHAMBER: &str = r#"north
+north
+north
+north
+north
+north
+north
+north
+north
+"#;
+pub const AFTER_VAULT: &str = r#"take mirror
+use mirror
+"#;
+
+// Feeds the commands and runs until the game asks for more.
+fn play(vm: &mut SynacorVm, commands: &str) -> Result<(), String> {
+    vm.feed(commands);
+    let budget = Budget::instructions(COMMAND_BUDGET * (commands.lines().count() as u64 + 1));
+    match vm.run_budgeted(&budget) {
+        Outcome::NeedInput => Ok(()),
+        outcome => Err(format!("{:?} while playing {:?}", outcome, commands.lines().next()))
+    }
+}
+
+/// The two scripts for SynacorVm::run, with the coin order and the vault route
+/// worked out on copies of `vm`, which must be at the start of the game with
+/// the confirmation routine dealt with, teleporting with `r7`.
+pub fn scripts(vm: &SynacorVm, r7: u16) -> Result<(String, String), String> {
+    let mut at_monument = vm.clone();
+    play(&mut at_monument, TO_MONUMENT)?;
+    let coins = coins::coin_commands(&at_monument).ok_or("no order of the coins fits the monument")?;
+    let prepared = format!("{}{}{}", TO_MONUMENT, coins, AFTER_COINS);
+
+    let mut at_antechamber = at_monument;
+    play(&mut at_antechamber, &format!("{}{}", coins, AFTER_COINS))?;
+    at_antechamber.set_register(7, r7);
+    play(&mut at_antechamber, &format!("use teleporter\n{}", TO_ANTECHAMBER))?;
+    at_antechamber.take_output();
+    let vault = vault::commands(&at_antechamber).map_err(|e| format!("vault: {}", e))?;
+    let second_prepared = format!("{}{}{}", TO_ANTECHAMBER, vault, AFTER_VAULT);
+    Ok((prepared, second_prepared))
+}
@@ -0,0 +1,67 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod common;
+
+use common::ENGINES;
+use synacor_challenge::synacor_vm::{Budget, CancelToken, Engine, Outcome, SynacorVm};
+
+// add r0 r0 1, jmp 0: runs until stopped
+const FOREVER: [u16; 6] = [9, 32768, 32768, 1, 6, 0];
+
+fn forever(engine: Engine) -> SynacorVm {
+    let mut vm = SynacorVm::new(FOREVER.to_vec());
+    vm.set_engine(engine);
+    vm
+}
+
+#[test]
+fn elapsed_deadlines_stop_every_engine() {
+    for &engine in ENGINES.iter() {
+        let mut vm = forever(engine);
+        let budget = Budget { instructions: Some(u64::MAX), ..Budget::timeout(Duration::ZERO) };
+        assert_eq!(vm.run_budgeted(&budget), Outcome::BudgetExhausted, "{:?}", engine);
+        assert_eq!((vm.steps(), vm.ip()), (0, 0), "{:?}", engine);
+
+        let started = Instant::now();
+        assert_eq!(vm.run_budgeted(&Budget::timeout(Duration::from_millis(50))), Outcome::BudgetExhausted, "{:?}", engine);
+        assert!(started.elapsed() >= Duration::from_millis(50), "{:?}", engine);
+        assert!(started.elapsed() < Duration::from_secs(10), "{:?}", engine);
+        let steps = vm.steps();
+        assert!(steps > 0, "{:?}", engine);
+
+        // resumes where it stopped
+        assert_eq!(vm.run_budgeted(&Budget::instructions(10)), Outcome::BudgetExhausted, "{:?}", engine);
+        assert_eq!(vm.steps(), steps + 10, "{:?}", engine);
+    }
+}
+
+#[test]
+fn cancelled_tokens_stop_every_engine() {
+    for &engine in ENGINES.iter() {
+        let mut vm = forever(engine);
+        let token = CancelToken::new();
+        token.cancel();
+        let budget = Budget { cancel: Some(token), ..Budget::default() };
+        assert_eq!(vm.run_budgeted(&budget), Outcome::Cancelled, "{:?}", engine);
+        assert_eq!((vm.steps(), vm.ip()), (0, 0), "{:?}", engine);
+
+        let token = CancelToken::new();
+        let canceller = {
+            let token = token.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                token.cancel();
+            })
+        };
+        let budget = Budget { cancel: Some(token), ..Budget::timeout(Duration::from_secs(10)) };
+        assert_eq!(vm.run_budgeted(&budget), Outcome::Cancelled, "{:?}", engine);
+        canceller.join().unwrap();
+        let steps = vm.steps();
+        assert!(steps > 0, "{:?}", engine);
+
+        // resumes where it stopped
+        assert_eq!(vm.run_budgeted(&Budget::instructions(10)), Outcome::BudgetExhausted, "{:?}", engine);
+        assert_eq!(vm.steps(), steps + 10, "{:?}", engine);
+    }
+}
@@ -0,0 +1,44 @@
+use synacor_challenge::codes::{self, Code, CodeDetector};
+
+#[test]
+fn mirror_swaps_and_reverses() {
+    assert_eq!(codes::mirror("qAddloUWuIAd"), "bAIuWUolbbAp");
+    assert_eq!(codes::mirror(&codes::mirror("qAddloUWuIAd")), "qAddloUWuIAd");
+    assert_eq!(codes::mirror("bdpq"), "pqbd");
+    // no mirror counterpart, kept as they are
+    assert_eq!(codes::mirror("AHzRk7"), "7kRzHA");
+    assert_eq!(codes::mirror("BDPQ"), "QPDB");
+    assert_eq!(codes::mirror(""), "");
+}
+
+#[test]
+fn find_codes_in_text() {
+    assert_eq!(codes::find_codes("Here's a code for the challenge website: oHVlEiuRDDqk\n"), vec!["oHVlEiuRDDqk"]);
+    assert_eq!(codes::find_codes("\"SaFPTyYYPxtc\" and xHUCoNHlSgmn."), vec!["SaFPTyYYPxtc", "xHUCoNHlSgmn"]);
+    // too short, too long, one case only, digits
+    assert!(codes::find_codes("oHVlEiuRDDq oHVlEiuRDDqkk ohvleiurddqk OHVLEIURDDQK oHVlEiuRDD1k").is_empty());
+    assert!(codes::find_codes("Interdimensional").is_empty());
+}
+
+#[test]
+fn detector_finds_codes_in_output() {
+    let mut detector = CodeDetector::new();
+    detector.command("take tablet");
+    assert!(detector.scan("Taken.\n\nWhat do you do?\n").is_empty());
+    detector.command("use tablet");
+    let found = detector.scan("You find yourself writing \"ycDPlkIuhlbC\" on the tablet.  Perhaps it's some kind of code?\n");
+    assert_eq!(found, vec![Code { text: "ycDPlkIuhlbC".to_string(), mirrored: false }]);
+    // seen again, not reported twice
+    assert!(detector.scan("ycDPlkIuhlbC").is_empty());
+
+    detector.command("use mirror");
+    let found = detector.scan("Through the mirror, you see \"qAddloUWuIAd\" scrawled in charcoal on your forehead.\n");
+    assert_eq!(found.len(), 1);
+    assert!(found[0].mirrored);
+    assert_eq!(found[0].submission(), "bAIuWUolbbAp");
+    assert_eq!(found[0].to_string(), "qAddloUWuIAd - seen in the mirror, submit bAIuWUolbbAp");
+
+    let submissions: Vec<_> = detector.codes().iter().map(Code::submission).collect();
+    assert_eq!(submissions, vec!["ycDPlkIuhlbC", "bAIuWUolbbAp"]);
+    assert_eq!(detector.codes()[0].to_string(), "ycDPlkIuhlbC - mirrored: CdlhuIklPDcy");
+}
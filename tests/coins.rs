@@ -0,0 +1,49 @@
+use synacor_challenge::coins::{self, Equation};
+
+fn coins(values: &[(&str, i64)]) -> Vec<(String, i64)> {
+    values.iter().map(|&(name, value)| (name.to_string(), value)).collect()
+}
+
+#[test]
+fn parse_equations() {
+    assert!(Equation::parse("_ + _ * _^2 + _^3 - _ = 399").is_some());
+    assert!(Equation::parse("-_ = -3").is_some());
+    assert!(Equation::parse("_*_^2*_ = 0").is_some());
+    assert_eq!(Equation::find("There is a strange monument:\n_ + _ * _^2 + _^3 - _ = 399\nInteresting."),
+               Equation::parse("_ + _ * _^2 + _^3 - _ = 399"));
+}
+
+#[test]
+fn parse_malformed_equations() {
+    for text in ["", "_ + _", "_ + _ = ", "_ + _ = x", "_ = 1 = 2", "_ + = 1", "_ _ = 1", "_ / _ = 1",
+                 "_^ = 1", "_^x = 1", "+ _ = 1", "_ + 2 = 3", "_^99999999999 = 1"] {
+        assert_eq!(Equation::parse(text), None, "{:?}", text);
+    }
+    assert_eq!(Equation::find("no equation here\n= 4"), None);
+}
+
+#[test]
+fn overflowing_exponents_do_not_hold() {
+    let equation = Equation::parse("_^4000000000 = 1").unwrap();
+    assert_eq!(coins::solve(&equation, &coins(&[("red coin", 2)])), None);
+    assert_eq!(coins::solve(&equation, &coins(&[("red coin", 1)])), Some(vec!["red coin".to_string()]));
+    let equation = Equation::parse("_^40 * _^40 + _ = 5").unwrap();
+    assert_eq!(coins::solve(&equation, &coins(&[("a", 3), ("b", 3), ("c", 5)])), None);
+}
+
+#[test]
+fn solve_the_monument() {
+    let equation = Equation::parse("_ + _ * _^2 + _^3 - _ = 399").unwrap();
+    let found = coins(&[("red coin", 2), ("corroded coin", 3), ("shiny coin", 5), ("concave coin", 7), ("blue coin", 9)]);
+    assert_eq!(coins::solve(&equation, &found).unwrap(),
+               vec!["blue coin", "red coin", "shiny coin", "concave coin", "corroded coin"]);
+    assert_eq!(coins::solve(&equation, &found[1..]), None);
+}
+
+#[test]
+fn coin_values() {
+    assert_eq!(coins::coin_value("A shiny coin. It has nine dots on one side."), Some(9));
+    assert_eq!(coins::coin_value("It has 2 dots on one side."), Some(2));
+    assert_eq!(coins::coin_value("It has a pentagon on one side."), Some(5));
+    assert_eq!(coins::coin_value("It has a picture of a cat."), None);
+}
@@ -0,0 +1,32 @@
+// Each test file uses what it needs of this.
+#![allow(dead_code, unused_imports)]
+
+use synacor_challenge::synacor_vm::Engine;
+
+pub use synacor_challenge::disasm::reg;
+
+/// Every engine, the interpreter first.
+pub const ENGINES: [Engine; 2] = [Engine::Interpreter, Engine::Blocks];
+
+/// The seed of the random tests, so they check the same cases on every run.
+pub const SEED: u64 = 0x5eed;
+
+// xorshift
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u16 {
+        (self.next() % n) as u16
+    }
+}
@@ -0,0 +1,21 @@
+use synacor_challenge::decode::Instr;
+use synacor_challenge::disasm;
+use synacor_challenge::read_input_u16;
+
+#[test]
+fn decodes_like_the_disassembler() {
+    let program = read_input_u16("input/challenge.bin");
+    for (&address, instruction) in disasm::code(&program).iter() {
+        let instr = Instr::decode(&program, address);
+        assert_eq!(address + instr.size(), instruction.next(), "{}", instruction);
+    }
+    // 6027: jt r0 6035
+    assert_eq!(Instr::decode(&program, 6027), Instr::Jt(32768, 6035));
+}
+
+#[test]
+fn arguments_past_the_end_read_as_zero() {
+    assert_eq!(Instr::decode(&[9, 32768], 0), Instr::Add(32768, 0, 0));
+    assert_eq!(Instr::decode(&[22, 19], 0), Instr::Unknown(22));
+    assert_eq!(Instr::decode(&[22, 19], 1), Instr::Out(0));
+}
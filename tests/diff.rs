@@ -0,0 +1,66 @@
+use synacor_challenge::diff::{self, Change};
+use synacor_challenge::explorer;
+use synacor_challenge::game;
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::SynacorVm;
+
+// 0: wmem 20 72, 3: wmem 21 105, 6: set r1 7, 9: push 3, 11: halt
+const PROGRAM: [u16; 12] = [16, 20, 72, 16, 21, 105, 1, 32769, 7, 2, 3, 0];
+
+fn program() -> SynacorVm {
+    let mut program = PROGRAM.to_vec();
+    program.resize(30, 0);
+    SynacorVm::new(program)
+}
+
+#[test]
+fn lists_registers_stack_and_memory() {
+    let before = program();
+    let mut after = before.clone();
+    after.track_memory_writes();
+    after.run_until_input();
+    let diff = diff::diff(&before, &after);
+    assert_eq!(diff.ip, Some((0, 11)));
+    assert_eq!(diff.registers, vec![(1, 0, 7)]);
+    assert_eq!(diff.stack, Some((vec![], vec![3])));
+    assert_eq!(diff.memory, vec![Change { start: 20, before: vec![0, 0], after: vec![72, 105], writers: vec![0, 3] }]);
+    assert_eq!(diff.to_string(), "ip: 0 -> 11\nr1: 0 -> 7\nstack from 0: [] -> [3]\n[20..22] [0, 0] -> \"Hi\" written at 0, 3\n");
+}
+
+#[test]
+fn writers_are_left_out_unless_tracked() {
+    let before = program();
+    let mut after = before.clone();
+    after.run_until_input();
+    assert!(diff::diff(&before, &after).memory[0].writers.is_empty());
+    assert!(diff::diff(&after, &after).is_empty());
+}
+
+#[test]
+fn separate_runs() {
+    let mut before = program();
+    before.patch(25, &[1]);
+    let mut after = program();
+    after.patch(24, &[2]);
+    after.patch(26, &[3]);
+    let starts: Vec<(usize, usize)> = diff::diff(&before, &after).memory.iter().map(|c| (c.start, c.end())).collect();
+    assert_eq!(starts, vec![(24, 27)]);
+    after.patch(25, &[1]);
+    let starts: Vec<(usize, usize)> = diff::diff(&before, &after).memory.iter().map(|c| (c.start, c.end())).collect();
+    assert_eq!(starts, vec![(24, 25), (26, 27)]);
+}
+
+#[test]
+fn taking_an_item_changes_where_it_lies() {
+    let mut before = game::started(&read_input_u16("input/challenge.bin")).unwrap();
+    before.track_memory_writes();
+    let volatile = explorer::volatile_words(&before).unwrap();
+    let mut after = before.clone();
+    game::play(&mut after, "take tablet").unwrap();
+    let changes: Vec<Change> = diff::diff(&before, &after).memory.into_iter()
+        .filter(|change| !volatile.contains(&change.start))
+        .collect();
+    assert_eq!(changes.len(), 1, "{:?}", changes);
+    assert_eq!(changes[0].after, vec![0]);
+    assert_eq!(changes[0].writers.len(), 1);
+}
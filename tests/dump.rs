@@ -0,0 +1,34 @@
+use synacor_challenge::dump::{self, Row};
+
+// 0: set r1 72, 3: out r1, 5: a word that is no opcode, 6: 'i', 7: an invalid operand
+const MEMORY: [u16; 8] = [1, 32769, 72, 19, 32769, 99, 105, 32776];
+
+#[test]
+fn rows_have_every_column() {
+    let rows = dump::rows(&MEMORY, 0..8);
+    assert_eq!(rows[0], Row { address: 0, word: 1, character: None, operand: None, instruction: Some("set r1 72".to_string()) });
+    assert_eq!(rows[1].operand.as_deref(), Some("r1"));
+    assert_eq!(rows[2].character, Some('H'));
+    assert_eq!(rows[2].instruction, None);
+    assert_eq!(rows[3].instruction.as_deref(), Some("out r1"));
+    assert_eq!(rows[5].character, Some('c'));
+    assert_eq!(rows[5].instruction, None);
+    assert_eq!((rows[6].character, rows[6].instruction.as_deref()), (Some('i'), None));
+    assert_eq!(rows[7].operand.as_deref(), Some("<32776>"));
+}
+
+#[test]
+fn disassembly_starts_at_the_range() {
+    // from the middle of `set`, 72 is no opcode and `out r1` is found again
+    let rows = dump::rows(&MEMORY, 2..5);
+    assert_eq!(rows.iter().map(|row| row.instruction.as_deref()).collect::<Vec<_>>(), vec![None, Some("out r1"), None]);
+    assert!(dump::rows(&MEMORY, 6..20).len() == 2);
+}
+
+#[test]
+fn dump_lines() {
+    assert_eq!(dump::dump(&MEMORY, 0..3), concat!(
+        "    0      1  0001                   set r1 72\n",
+        "    1  32769  8001          r1\n",
+        "    2     72  0048  'H'\n"));
+}
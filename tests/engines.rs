@@ -0,0 +1,116 @@
+mod common;
+
+use common::{reg, ENGINES};
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::{Budget, Engine, Outcome, SynacorVm};
+
+// Everything observable about a VM, to compare engines with.
+fn state(vm: &mut SynacorVm) -> (String, usize, u64, Vec<u16>, Vec<u16>) {
+    let registers = (0..8).map(|r| vm.register(r)).collect();
+    (vm.take_output(), vm.ip(), vm.steps(), registers, vm.memory().to_vec())
+}
+
+// Runs `program` with each engine, feeding one command per prompt, and checks
+// they all stop the same way in the same state.
+fn cross_check(program: &[u16], commands: &[&str], budget: &Budget) {
+    let mut expected = None;
+    for &engine in ENGINES.iter() {
+        let mut vm = SynacorVm::new(program.to_vec());
+        vm.set_engine(engine);
+        let mut outcomes = Vec::new();
+        let mut commands = commands.iter();
+        loop {
+            let outcome = vm.run_budgeted(budget);
+            outcomes.push(outcome);
+            if outcome != Outcome::NeedInput { break; }
+            match commands.next() {
+                Some(command) => vm.feed(&format!("{}\n", command)),
+                None => break
+            }
+        }
+        let result = (outcomes, state(&mut vm));
+        match &expected {
+            None => expected = Some(result),
+            Some(expected) => assert!(*expected == result, "{:?} differs from the interpreter", engine)
+        }
+    }
+}
+
+#[test]
+fn engines_agree_on_the_challenge() {
+    let program = read_input_u16("input/challenge.bin");
+    let commands = ["take tablet", "use tablet", "doorway", "north", "north", "bridge", "continue", "down",
+                    "east", "take empty lantern", "west", "west", "passage", "ladder", "inv"];
+    cross_check(&program, &commands, &Budget::default());
+}
+
+#[test]
+fn engines_agree_on_budgets() {
+    let program = read_input_u16("input/challenge.bin");
+    for &limit in [0, 1, 63, 64, 65, 1000, 123_457].iter() {
+        cross_check(&program, &[], &Budget::instructions(limit));
+    }
+}
+
+#[test]
+fn self_modifying_code() {
+    let program = [
+        1, reg(0), 3,                // 0: set r0 3
+        9, reg(1), reg(1), 1,        // 3: add r1 r1 1
+        16, 6, 2,                    // 7: wmem 6 2, the add now adds 2
+        9, reg(0), reg(0), 32767,    // 10: add r0 r0 -1
+        7, reg(0), 3,                // 14: jt r0 3
+        19, reg(1),                  // 17: out r1
+        0                            // 19: halt
+    ];
+    cross_check(&program, &[], &Budget::default());
+
+    let mut vm = SynacorVm::new(program.to_vec());
+    vm.set_engine(Engine::Blocks);
+    assert_eq!(vm.run_until_input(), Outcome::Halted);
+    assert_eq!(vm.register(1), 5);
+    assert_eq!(vm.ip(), 19);
+}
+
+#[test]
+fn patches_reach_compiled_code() {
+    let program = [
+        9, reg(0), reg(0), 1,    // 0: add r0 r0 1
+        20, reg(1),              // 4: in r1
+        6, 0                     // 6: jmp 0
+    ];
+    for &engine in ENGINES.iter() {
+        let mut vm = SynacorVm::new(program.to_vec());
+        vm.set_engine(engine);
+        assert_eq!(vm.run_until_input(), Outcome::NeedInput);
+        vm.patch(3, &[10]);
+        vm.feed("x");
+        assert_eq!(vm.run_until_input(), Outcome::NeedInput);
+        assert_eq!(vm.register(0), 11, "{:?}", engine);
+    }
+}
+
+#[test]
+fn hooks_reach_compiled_code() {
+    let program = [
+        17, 6,                      // 0: call 6
+        19, reg(0),                 // 2: out r0
+        0,                          // 4: halt
+        21,                         // 5: noop
+        1, reg(0), 65,              // 6: set r0 'A'
+        9, reg(0), reg(0), 1,       // 9: add r0 r0 1
+        18                          // 13: ret
+    ];
+    for &engine in ENGINES.iter() {
+        let mut vm = SynacorVm::new(program.to_vec());
+        vm.set_engine(engine);
+        assert_eq!(vm.run_until_input(), Outcome::Halted);
+        assert_eq!(vm.take_output(), "B");
+
+        // in the middle of the block compiled from 6
+        vm.hook(9, |vm| vm.set_register(0, 90));
+        vm.jump(0);
+        assert_eq!(vm.run_until_input(), Outcome::Halted);
+        assert_eq!(vm.take_output(), "Z", "{:?}", engine);
+    }
+}
@@ -0,0 +1,110 @@
+use std::ops::Range;
+
+use synacor_challenge::explorer::{self, World};
+use synacor_challenge::fnv::Fnv;
+use synacor_challenge::game;
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::SynacorVm;
+
+fn start() -> SynacorVm {
+    game::started(&read_input_u16("input/challenge.bin")).unwrap()
+}
+
+fn explore(vm: &SynacorVm) -> World {
+    explorer::explore(vm, 1000).unwrap()
+}
+
+#[test]
+fn volatile_words_are_the_input_buffer() {
+    let volatile = explorer::volatile_words(&start()).unwrap();
+    assert!(!volatile.is_empty() && volatile.len() <= 41);
+    assert!(volatile.windows(2).all(|w| w[1] == w[0] + 1));
+}
+
+#[test]
+fn maze_rooms_are_told_apart() {
+    let world = explore(&start());
+    let count = |name: &str| world.places.iter().filter(|place| place.room.name == name).count();
+    assert_eq!(world.places[0].room.name, "Foothills");
+    assert!(count("Twisty passages") > 10);
+    assert_eq!(count("Rope bridge"), 1);
+}
+
+#[test]
+fn paths_lead_to_their_room() {
+    let vm = start();
+    let world = explore(&vm);
+    let volatile = explorer::volatile_words(&vm).unwrap();
+    for place in world.places.iter() {
+        let mut replay = vm.clone();
+        for command in place.path.iter() {
+            game::play(&mut replay, command).unwrap();
+        }
+        let room = game::play(&mut replay, "look").unwrap().room.unwrap();
+        assert_eq!(room, place.room);
+        assert_eq!(explorer::fingerprint(&room, replay.memory(), &volatile), place.fingerprint, "{:?}", place.path);
+        assert!(place.exits.values().all(|&to| to < world.places.len()));
+    }
+}
+
+#[test]
+fn exports() {
+    let world = explore(&start());
+    let json = world.to_json();
+    let rooms = json["rooms"].as_array().unwrap();
+    assert_eq!(rooms.len(), world.places.len());
+    assert_eq!(rooms[1]["path"], serde_json::json!(["doorway"]));
+    assert_eq!(rooms[0]["exits"]["doorway"], 1);
+
+    let dot = world.to_dot();
+    assert!(dot.starts_with("digraph world {\n"));
+    assert!(dot.contains("    0 -> 1 [label=\"doorway\"];\n"));
+}
+
+#[test]
+fn location_word_holds_the_current_room() {
+    let vm = start();
+    let world = explorer::explore(&vm, 12).unwrap();
+    let words = explorer::location_words(&vm, &world).unwrap();
+    assert!(words.contains(&2732), "{:?}", words);
+
+    // going through the doorway and back brings the same value, not the same path
+    let regions: Vec<Range<usize>> = words.iter().map(|&word| word..word + 1).collect();
+    let location = |vm: &SynacorVm| vm.hash_regions(&regions);
+    let mut there_and_back = vm.clone();
+    game::play(&mut there_and_back, "doorway").unwrap();
+    assert_ne!(location(&there_and_back), location(&vm));
+    game::play(&mut there_and_back, "south").unwrap();
+    assert_eq!(location(&there_and_back), location(&vm));
+}
+
+#[test]
+fn inverted_regions_hash_as_empty() {
+    let vm = start();
+    let (start, end) = (2740, 2732);
+    assert_eq!(vm.hash_regions(&[start..end, start + 50000..end + 40000]), vm.hash_regions(&[]));
+    assert_eq!(vm.hash_regions(&[end..end + 1, start..end]), vm.hash_regions(&[end..end + 1, 0..0]));
+}
+
+#[test]
+fn rooms_are_places_by_location() {
+    let world = explorer::explore_rooms(&start(), 1000).unwrap();
+    let count = |name: &str| world.places.iter().filter(|place| place.room.name == name).count();
+    assert!(world.places.len() < explore(&start()).places.len());
+    assert_eq!(count("Twisty passages"), 8);
+    assert_eq!(count("Rope bridge"), 1);
+}
+
+#[test]
+fn fingerprints_use_a_fixed_hash() {
+    // published FNV-1a test vectors
+    assert_eq!(Fnv::new().finish(), 0xcbf2_9ce4_8422_2325);
+    let mut hasher = Fnv::new();
+    hasher.bytes(b"a");
+    assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    let mut words = Fnv::new();
+    words.words(&[0x6261]);
+    let mut bytes = Fnv::new();
+    bytes.bytes(b"ab");
+    assert_eq!(words.finish(), bytes.finish());
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{reg, Rng, SEED};
+use synacor_challenge::bytes_to_u16;
+use synacor_challenge::synacor_vm::{Budget, Fault, Outcome, SynacorVm};
+
+fn run(program: &[u16]) -> (Outcome, SynacorVm) {
+    let mut vm = SynacorVm::new(program.to_vec());
+    let outcome = vm.run_budgeted(&Budget::instructions(1000));
+    (outcome, vm)
+}
+
+#[test]
+fn faults_stop_at_the_instruction() {
+    let cases: [(&[u16], Fault, usize); 10] = [
+        (&[1, reg(0), 32776], Fault::InvalidOperand(32776), 0),
+        (&[1, 5, 6], Fault::NotARegister(5), 0),
+        (&[21, 3, reg(0)], Fault::EmptyStack, 1),
+        (&[11, reg(0), 7, 0], Fault::DivisionByZero, 0),
+        (&[15, reg(0), 100], Fault::AddressOutOfRange(100), 0),
+        (&[16, 100, 1], Fault::AddressOutOfRange(100), 0),
+        (&[15, reg(0), 4, 0, 40000], Fault::InvalidValue(40000), 0),
+        (&[2, 1, 3, 3], Fault::NotARegister(3), 2),
+        (&[17, 32776], Fault::InvalidOperand(32776), 0),
+        (&[19, 65, 22], Fault::UnknownOpcode(22), 2)
+    ];
+    for &(program, fault, ip) in cases.iter() {
+        let (outcome, vm) = run(program);
+        assert_eq!(outcome, Outcome::Fault(fault), "{:?}", program);
+        assert_eq!(vm.ip(), ip);
+    }
+}
+
+#[test]
+fn call_without_a_return_address() {
+    let mut program = vec![6, 32766];
+    program.resize(32768, 0);
+    program[32766] = 17;
+    let (outcome, vm) = run(&program);
+    assert_eq!(outcome, Outcome::Fault(Fault::AddressOutOfRange(32768)));
+    assert_eq!(vm.ip(), 32766);
+}
+
+#[test]
+fn faulting_pop_keeps_the_stack() {
+    let (outcome, vm) = run(&[2, 1, 3, 3]);
+    assert_eq!(outcome, Outcome::Fault(Fault::NotARegister(3)));
+    assert_eq!(vm.stack(), &[1]);
+}
+
+#[test]
+fn input_too_large_for_a_register() {
+    let mut vm = SynacorVm::new(vec![20, reg(0), 0]);
+    vm.feed("\u{10000}");
+    assert_eq!(vm.run_until_input(), Outcome::Fault(Fault::InvalidInput('\u{10000}')));
+}
+
+#[test]
+fn odd_bytes_make_a_last_word() {
+    assert_eq!(bytes_to_u16(&[1, 2, 3]), vec![0x0201, 3]);
+}
+
+// Same checks as fuzz/fuzz_targets/vm.rs, on random bytes.
+#[test]
+fn random_programs_never_panic() {
+    let mut rng = Rng::new(SEED);
+    for _ in 0..2000 {
+        let bytes: Vec<u8> = (0..64)
+            .map(|_| {
+                let seed = rng.next();
+                // mostly small words, so opcodes and registers come up often
+                match seed % 4 {
+                    0 => (seed >> 8) as u8 % 22,
+                    1 => 0x80,
+                    _ => (seed >> 16) as u8
+                }
+            })
+            .collect();
+        let mut vm = SynacorVm::new(bytes_to_u16(&bytes));
+        vm.feed("look\n");
+        let outcome = vm.run_budgeted(&Budget::instructions(10_000));
+        assert!((0..8).all(|r| vm.register(r) <= 32767));
+        assert!(vm.stack().iter().all(|&x| x <= 32767));
+        match outcome {
+            Outcome::OutOfMemory => assert!(vm.ip() >= vm.memory().len()),
+            Outcome::BudgetExhausted => {}
+            Outcome::Fault(Fault::UnknownOpcode(opcode)) => assert!(opcode > 21 && vm.memory().get(vm.ip()) == Some(&opcode)),
+            _ => assert!(vm.ip() < vm.memory().len())
+        }
+    }
+}
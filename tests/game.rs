@@ -0,0 +1,62 @@
+use synacor_challenge::game::{self, GameView, Room};
+use synacor_challenge::read_input_u16;
+
+#[test]
+fn room_with_items_and_exits() {
+    let output = "\n\n== Foothills ==\nYou find yourself standing at the base of an enormous mountain.\n\n\
+                  Things of interest here:\n- tablet\n\nThere are 2 exits:\n- doorway\n- south\n\nWhat do you do?\n";
+    assert_eq!(GameView::parse(output), GameView {
+        room: Some(Room {
+            name: "Foothills".to_string(),
+            description: "You find yourself standing at the base of an enormous mountain.".to_string(),
+            items: vec!["tablet".to_string()],
+            exits: vec!["doorway".to_string(), "south".to_string()]
+        }),
+        inventory: None,
+        messages: vec![],
+        prompt: true
+    });
+}
+
+#[test]
+fn description_over_several_paragraphs() {
+    let output = "== Ruins ==\nThere is a strange monument.  It reads:\n\n_ + _ * _^2 + _^3 - _ = 399\n\n\
+                  There is 1 exit:\n- north\n\nWhat do you do?\n";
+    let room = GameView::parse(output).room.unwrap();
+    assert_eq!(room.description, "There is a strange monument.  It reads:\n\n_ + _ * _^2 + _^3 - _ = 399");
+    assert_eq!(room.exits, vec!["north"]);
+    assert!(room.items.is_empty());
+}
+
+#[test]
+fn inventory_and_messages() {
+    let empty = GameView::parse("\n\nYour inventory:\n\nWhat do you do?\n");
+    assert_eq!(empty.inventory, Some(vec![]));
+    assert_eq!(empty.room, None);
+
+    let view = GameView::parse("\n\nTaken.\n\nWhat do you do?\n");
+    assert_eq!(view.messages, vec!["Taken."]);
+    assert_eq!(view.inventory, None);
+    assert!(view.prompt);
+}
+
+#[test]
+fn message_before_a_room() {
+    let output = "You activate the teleporter!\nYou feel dizzy.\n\n== Beach ==\nSand.\n\n\
+                  There are 2 exits:\n- west\n- north\n\nWhat do you do?\n";
+    let view = GameView::parse(output);
+    assert_eq!(view.messages, vec!["You activate the teleporter!\nYou feel dizzy."]);
+    assert_eq!(view.room.unwrap().name, "Beach");
+}
+
+#[test]
+fn playing_the_game() {
+    let mut vm = game::started(&read_input_u16("input/challenge.bin")).unwrap();
+    let start = GameView::parse(&vm.take_output());
+    assert_eq!(start.room.unwrap().items, vec!["tablet"]);
+
+    game::play(&mut vm, "take tablet").unwrap();
+    assert_eq!(game::play(&mut vm, "inv").unwrap().inventory, Some(vec!["tablet".to_string()]));
+    let view = game::play(&mut vm, "doorway").unwrap();
+    assert_eq!(view.room.unwrap().name, "Dark cave");
+}
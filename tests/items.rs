@@ -0,0 +1,46 @@
+use synacor_challenge::explorer;
+use synacor_challenge::game;
+use synacor_challenge::items::{self, Interaction};
+use synacor_challenge::read_input_u16;
+
+fn interactions() -> Vec<Interaction> {
+    let mut vm = game::started(&read_input_u16("input/challenge.bin")).unwrap();
+    vm.take_output();
+    let world = explorer::explore(&vm, 1000).unwrap();
+    items::interactions(&vm, &world).unwrap()
+}
+
+fn find<'a>(interactions: &'a [Interaction], commands: &[&str]) -> &'a Interaction {
+    interactions.iter()
+        .find(|i| i.place == 0 && i.commands == commands)
+        .unwrap_or_else(|| panic!("{:?} not tried", commands))
+}
+
+#[test]
+fn tablet_in_the_foothills() {
+    let interactions = interactions();
+
+    let take = find(&interactions, &["take tablet"]);
+    assert!(take.memory_changed);
+    assert_eq!(take.messages, vec!["Taken."]);
+    assert!(!take.is_interesting());
+
+    let look = find(&interactions, &["look tablet"]);
+    assert!(!look.memory_changed);
+
+    let used = find(&interactions, &["take tablet", "use tablet"]);
+    assert_eq!(used.codes, vec!["ycDPlkIuhlbC"]);
+    assert!(used.is_interesting());
+    assert_eq!(used.to_string(), "0: take tablet, then use tablet, code ycDPlkIuhlbC, changes the game");
+}
+
+#[test]
+fn lantern_and_can_are_found() {
+    let interactions = interactions();
+    let taken: Vec<&str> = interactions.iter()
+        .filter(|i| i.commands.len() == 1 && i.commands[0].starts_with("take ") && i.memory_changed)
+        .map(|i| i.commands[0].as_str())
+        .collect();
+    assert!(taken.contains(&"take empty lantern"));
+    assert!(taken.contains(&"take can"));
+}
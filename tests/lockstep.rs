@@ -0,0 +1,112 @@
+mod common;
+
+use common::{reg, Rng, SEED};
+use synacor_challenge::lockstep::lockstep;
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::{Engine, Outcome, SynacorVm};
+
+fn vm(program: &[u16], engine: Engine) -> SynacorVm {
+    let mut vm = SynacorVm::new(program.to_vec());
+    vm.set_engine(engine);
+    vm
+}
+
+// Random instructions that can't fail: operands are in range, memory covers
+// every address, jumps and calls land on instructions, reads and writes go past the code,
+// divisors are non-zero literals and every pop follows a push.
+fn random_program(rng: &mut Rng, len: usize) -> Vec<u16> {
+    let mut instructions: Vec<Vec<u16>> = Vec::new();
+    for _ in 0..len {
+        let register = 32768 + rng.below(8);
+        let value = if rng.below(2) == 0 { 32768 + rng.below(8) } else { rng.below(32768) };
+        let data = 1000 + rng.below(32768 - 1000);
+        let opcode = rng.below(22);
+        // jump targets are filled in below
+        instructions.push(match opcode {
+            0 if rng.below(8) != 0 => continue,
+            3 => vec![2, value, 3, register],
+            2 | 19 => vec![opcode, value],
+            6 | 17 => vec![opcode, 0],
+            7 | 8 => vec![opcode, value, 0],
+            11 => vec![opcode, register, value, 1 + rng.below(32767)],
+            15 => vec![opcode, register, data],
+            16 => vec![opcode, data, value],
+            1 | 14 => vec![opcode, register, value],
+            20 => vec![opcode, register],
+            4 | 5 | 9 | 10 | 12 | 13 => vec![opcode, register, value, value],
+            _ => vec![opcode]
+        });
+    }
+    let starts: Vec<usize> = instructions.iter()
+        .scan(0, |address, instruction| { *address += instruction.len(); Some(*address - instruction.len()) })
+        .collect();
+    let mut program = Vec::new();
+    for mut instruction in instructions {
+        let target = starts[rng.below(starts.len() as u64) as usize] as u16;
+        if matches!(instruction[0], 6..=8 | 17) {
+            *instruction.last_mut().unwrap() = target;
+        }
+        program.extend(instruction);
+    }
+    assert!(program.len() < 1000);
+    program.resize(32768, 0);
+    program
+}
+
+#[test]
+fn interpreter_and_blocks_agree_on_the_challenge() {
+    let program = read_input_u16("input/challenge.bin");
+    let steps = lockstep(&mut vm(&program, Engine::Interpreter), &mut vm(&program, Engine::Blocks), &["look"], u64::MAX)
+        .unwrap_or_else(|mismatch| panic!("{}", mismatch));
+    assert!(steps > 500_000);
+}
+
+#[test]
+fn engines_agree_on_random_programs() {
+    let mut rng = Rng::new(SEED);
+    for _ in 0..200 {
+        let program = random_program(&mut rng, 100);
+        if let Err(mismatch) = lockstep(&mut vm(&program, Engine::Interpreter), &mut vm(&program, Engine::Blocks), &["abc", "d"], 5000) {
+            panic!("the interpreter and blocks differ on {:?}\n{}", &program[..200], mismatch);
+        }
+    }
+}
+
+#[test]
+fn blocks_step_a_block_at_a_time() {
+    let program = [
+        9, reg(0), reg(0), 1,    // 0: add r0 r0 1
+        9, reg(1), reg(0), 1,    // 4: add r1 r0 1
+        6, 0                 // 8: jmp 0
+    ];
+    let mut interpreter = vm(&program, Engine::Interpreter);
+    let mut blocks = vm(&program, Engine::Blocks);
+    assert_eq!(interpreter.step(), Outcome::BudgetExhausted);
+    assert_eq!(blocks.step(), Outcome::BudgetExhausted);
+    assert_eq!((interpreter.steps(), interpreter.ip()), (1, 4));
+    assert_eq!((blocks.steps(), blocks.ip()), (3, 0));
+    let (mut interpreter, mut blocks) = (vm(&program, Engine::Interpreter), vm(&program, Engine::Blocks));
+    assert_eq!(lockstep(&mut interpreter, &mut blocks, &[], 30), Ok(30));
+    assert_eq!(blocks.steps(), 30);
+}
+
+#[test]
+fn reports_the_first_mismatch() {
+    let program = [
+        9, reg(0), reg(0), 1,    // 0: add r0 r0 1
+        9, reg(1), reg(0), 1,    // 4: add r1 r0 1
+        19, reg(1),            // 8: out r1
+        0                    // 10: halt
+    ];
+    let mut left = vm(&program, Engine::Interpreter);
+    let mut right = vm(&program, Engine::Interpreter);
+    right.set_register(0, 1);
+    let mismatch = lockstep(&mut left, &mut right, &[], 100).unwrap_err();
+    assert_eq!(mismatch.step, 0);
+    assert_eq!(mismatch.address, 0);
+    assert_eq!(mismatch.field, "registers");
+    assert_eq!(mismatch.left, "r0 = 1");
+    assert_eq!(mismatch.right, "r0 = 2");
+    assert_eq!(mismatch.context[0], "> 0: add r0 r0 1");
+    assert_eq!(mismatch.context[1], "  4: add r1 r0 1");
+}
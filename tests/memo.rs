@@ -0,0 +1,72 @@
+use synacor_challenge::analysis;
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::{Outcome, SynacorVm};
+
+use std::collections::HashMap;
+
+// the confirmation routine the teleporter calls
+const CONFIRMATION: usize = 6027;
+
+// The challenge starting at `push 1234, call <entry>`, ready to run that with
+// the given registers. After the call the registers then the popped word are
+// written to the 9 words at the end of memory, and `ret` only halts if the
+// stack is left empty.
+fn calling(entry: usize, registers: &[(usize, u16)]) -> SynacorVm {
+    let mut program = read_input_u16("input/challenge.bin");
+    let start = program.len();
+    let saved = start + 40;
+    program.extend_from_slice(&[2, 1234, 17, entry as u16]);
+    for r in 0..8 {
+        program.extend_from_slice(&[16, (saved + r) as u16, 32768 + r as u16]);
+    }
+    program.extend_from_slice(&[3, 32768, 16, (saved + 8) as u16, 32768, 18]);
+    program.resize(saved + 9, 0);
+    let mut vm = SynacorVm::new(program);
+    for &(r, value) in registers.iter() {
+        vm.set_register(r, value);
+    }
+    vm.patch(0, &[6, start as u16]);
+    vm
+}
+
+fn state(vm: &SynacorVm) -> &[u16] {
+    &vm.memory()[vm.memory().len() - 9..]
+}
+
+#[test]
+fn memoized_confirmation_matches_plain_execution() {
+    for &(m, n, r7) in [(2, 3, 1), (3, 2, 2), (3, 1, 4), (1, 7, 32767)].iter() {
+        let registers = [(0, m), (1, n), (7, r7)];
+        let mut plain = calling(CONFIRMATION, &registers);
+        let mut memoized = calling(CONFIRMATION, &registers);
+        assert!(memoized.memoize_at(CONFIRMATION));
+        assert_eq!(plain.run_until_input(), Outcome::Halted);
+        assert_eq!(memoized.run_until_input(), Outcome::Halted);
+        assert_eq!(state(&memoized), state(&plain), "m = {}, n = {}, r7 = {}", m, n, r7);
+        assert_eq!(state(&memoized)[8], 1234);
+    }
+}
+
+#[test]
+fn memoizing_every_pure_function_keeps_the_stack() {
+    let registers = [(0, 3), (1, 3), (7, 3)];
+    let mut plain = calling(CONFIRMATION, &registers);
+    let mut memoized = calling(CONFIRMATION, &registers);
+    assert!(memoized.memoize_pure_functions() > 0);
+    assert_eq!(plain.run_until_input(), Outcome::Halted);
+    assert_eq!(memoized.run_until_input(), Outcome::Halted);
+    assert_eq!(state(&memoized), state(&plain));
+}
+
+#[test]
+fn unbalanced_stacks_are_not_pure() {
+    // 0: push r0, 2: ret -- leaves a word behind
+    assert_eq!(analysis::pure_function(&[2, 32768, 18], 0, &HashMap::new()), None);
+    // 0: pop r0, 2: ret -- takes one of the caller's words
+    assert_eq!(analysis::pure_function(&[3, 32768, 18], 0, &HashMap::new()), None);
+    // 0: jt r0 5, 3: push r1, 5: ret -- depends on the branch
+    assert_eq!(analysis::pure_function(&[7, 32768, 5, 2, 32769, 18], 0, &HashMap::new()), None);
+    // 0: push r0, 2: add r0 r0 1, 6: pop r1, 8: ret
+    let balanced = analysis::pure_function(&[2, 32768, 9, 32768, 32768, 1, 3, 32769, 18], 0, &HashMap::new()).unwrap();
+    assert_eq!((balanced.inputs, balanced.outputs), (vec![0], vec![0, 1]));
+}
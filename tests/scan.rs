@@ -0,0 +1,54 @@
+use synacor_challenge::game;
+use synacor_challenge::read_input_u16;
+use synacor_challenge::scan::{self, Predicate, Scan};
+use synacor_challenge::synacor_vm::SynacorVm;
+
+fn start() -> SynacorVm {
+    game::started(&read_input_u16("input/challenge.bin")).unwrap()
+}
+
+#[test]
+fn finds_patterns_and_strings() {
+    let memory = [3, 97, 98, 99, 0, 97, 98, 99, 3, 97, 98];
+    assert_eq!(scan::find(&memory, &[97, 98]), vec![1, 5, 9]);
+    assert_eq!(scan::find(&memory, &[]), Vec::<usize>::new());
+    assert_eq!(scan::find_text(&memory, "abc"), vec![1, 5]);
+    assert_eq!(scan::find_counted_text(&memory, "abc"), vec![0]);
+}
+
+#[test]
+fn predicates() {
+    let mut vm = SynacorVm::new(vec![5, 5, 5, 5]);
+    let mut scan = Scan::all(&vm);
+    vm.patch(0, &[6]);
+    vm.patch(1, &[4]);
+    vm.patch(2, &[9]);
+    assert_eq!(scan.clone().narrow(&vm, Predicate::Changed).candidates(), vec![0, 1, 2]);
+    assert_eq!(scan.clone().narrow(&vm, Predicate::Unchanged).candidates(), vec![3]);
+    assert_eq!(scan.clone().narrow(&vm, Predicate::Increased).candidates(), vec![0, 2]);
+    assert_eq!(scan.clone().narrow(&vm, Predicate::Decreased).candidates(), vec![1]);
+    assert_eq!(scan.narrow(&vm, Predicate::EqualTo(9)).candidates(), vec![2]);
+}
+
+#[test]
+fn finds_the_player_location() {
+    let mut vm = start();
+    let mut scan = Scan::all(&vm);
+    for (command, predicate) in [("doorway", Predicate::Changed), ("look", Predicate::Unchanged),
+                                 ("north", Predicate::Changed), ("inv", Predicate::Unchanged),
+                                 ("south", Predicate::Changed)].iter() {
+        game::play(&mut vm, command).unwrap();
+        scan.narrow(&vm, *predicate);
+    }
+    assert_eq!(scan.candidates(), vec![2732, 2733]);
+}
+
+#[test]
+fn finds_where_an_item_lies() {
+    let mut vm = start();
+    // the tablet lies in the first room, and in the inventory once taken
+    let mut scan = Scan::value(&vm, vm.memory()[2732]);
+    game::play(&mut vm, "take tablet").unwrap();
+    scan.narrow(&vm, Predicate::EqualTo(0));
+    assert_eq!(scan.candidates(), vec![2670]);
+}
@@ -0,0 +1,59 @@
+use synacor_challenge::read_input_u16;
+use synacor_challenge::solve::{self, CODE_COUNT};
+use synacor_challenge::synacor_vm::{SynacorVm, TELEPORTER_R7};
+use synacor_challenge::teleporter::{self, Bypass};
+use synacor_challenge::walkthrough;
+
+#[test]
+fn replay_directives() {
+    let program = read_input_u16("input/challenge.bin");
+    let site = teleporter::find_call_site(&program).unwrap();
+    let bypass = Bypass::new(&site);
+    let mut vm = SynacorVm::new(program.clone());
+    vm.patch(bypass.address, &bypass.words);
+    vm.run_until_input();
+    let (prepared, second_prepared) = walkthrough::scripts(&vm, TELEPORTER_R7).unwrap();
+    let script = format!("!{}\ntake tablet\nuse tablet\n{}!r7 {}\nuse teleporter\n{}",
+                         bypass, prepared, TELEPORTER_R7, second_prepared);
+
+    let (output, codes) = solve::replay(&program, &script).unwrap();
+    assert!(output.contains("== Vault =="));
+    let codes: Vec<String> = codes.iter().map(|code| code.submission()).collect();
+    assert_eq!(codes, vec!["SaFPTyYYPxtc", "xHUCoNHlSgmn", "ycDPlkIuhlbC", "frOgQzZlguet", "buMeVvBwqgHJ",
+                           "uQWfNPkCwlXV", "bAIuWUolbbAp"]);
+}
+
+#[test]
+fn replay_errors() {
+    let program = read_input_u16("input/challenge.bin");
+    assert_eq!(solve::replay(&program, "look\n!r7 x\n").unwrap_err(), "line 2 \"!r7 x\": bad value");
+    assert_eq!(solve::replay(&program, "!patch 40000: 1\n").unwrap_err(),
+               "line 1 \"!patch 40000: 1\": patch past the end of memory");
+    let error = solve::replay(&program, "!patch 0 1\n").unwrap_err();
+    assert!(error.contains("expected !patch"), "{}", error);
+}
+
+#[test]
+fn solves_from_scratch() {
+    let program = read_input_u16("input/challenge.bin");
+    let spec = std::fs::read_to_string("support/arch-spec").unwrap();
+    let solution = solve::solve(&program, &spec).unwrap();
+    let codes: Vec<String> = solution.codes.iter().map(|code| code.submission()).collect();
+    assert_eq!(codes, vec!["oHVlEiuRDDqk", "SaFPTyYYPxtc", "xHUCoNHlSgmn", "frOgQzZlguet", "ycDPlkIuhlbC",
+                           "buMeVvBwqgHJ", "uQWfNPkCwlXV", "bAIuWUolbbAp"]);
+    // found by trying the items, not the walkthrough's
+    assert!(solution.script.contains("take can\nuse tablet\nuse can\nuse lantern\n"));
+    assert!(solution.script.contains(&format!("!r7 {}\nuse teleporter\n", TELEPORTER_R7)));
+    let (output, codes) = solve::replay(&program, &solution.script).unwrap();
+    assert!(output.contains("== Vault =="));
+    assert_eq!(codes.len(), CODE_COUNT - 1);
+}
+
+#[test]
+fn reports_missing_codes() {
+    let program = read_input_u16("input/challenge.bin");
+    let spec = std::fs::read_to_string("support/arch-spec").unwrap();
+    // the teleporter only ever goes to headquarters
+    let error = solve::solve_with_r7(&program, &spec, 0).unwrap_err();
+    assert!(error.starts_with("found 6 codes out of 8: oHVlEiuRDDqk, "), "{}", error);
+}
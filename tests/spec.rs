@@ -0,0 +1,327 @@
+// One or more programs per opcode of support/arch-spec, run on every engine.
+
+mod common;
+
+use common::{reg, ENGINES};
+use synacor_challenge::disasm::OPCODES;
+use synacor_challenge::synacor_vm::{Budget, Fault, Outcome, SynacorVm};
+
+// Assembles one instruction per line, e.g. "add r0 r1 4"; operands are
+// numbers or registers r0 to r7.
+fn asm(source: &str) -> Vec<u16> {
+    source.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .flat_map(|line| {
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap();
+            let opcode = OPCODES.iter().position(|&(n, _)| n == name).unwrap_or_else(|| panic!("no opcode {}", name));
+            let args: Vec<u16> = words
+                .map(|arg| match arg.strip_prefix('r') {
+                    Some(r) => reg(r.parse().unwrap()),
+                    None => arg.parse().unwrap()
+                })
+                .collect();
+            assert_eq!(args.len(), OPCODES[opcode].1, "{}", line);
+            Some(opcode as u16).into_iter().chain(args)
+        })
+        .collect()
+}
+
+struct Run {
+    outcome: Outcome,
+    registers: Vec<u16>,
+    stack: Vec<u16>,
+    output: String,
+    memory: Vec<u16>,
+    ip: usize
+}
+
+// Runs the program on every engine, with `registers` preset, checks they all
+// end the same way and returns how.
+fn run_with(program: &[u16], registers: &[(usize, u16)], input: &str) -> Run {
+    let runs: Vec<Run> = ENGINES.iter().map(|&engine| {
+        let mut vm = SynacorVm::new(program.to_vec());
+        vm.set_engine(engine);
+        for &(r, value) in registers.iter() {
+            vm.set_register(r, value);
+        }
+        vm.feed(input);
+        let outcome = vm.run_budgeted(&Budget::instructions(10_000));
+        Run {
+            outcome,
+            registers: (0..8).map(|r| vm.register(r)).collect(),
+            stack: vm.stack().to_vec(),
+            output: vm.take_output(),
+            memory: vm.memory().to_vec(),
+            ip: vm.ip()
+        }
+    }).collect();
+    for (run, engine) in runs.iter().zip(ENGINES.iter()).skip(1) {
+        let first = &runs[0];
+        assert_eq!((run.outcome, &run.registers, &run.stack, &run.output, run.ip),
+                   (first.outcome, &first.registers, &first.stack, &first.output, first.ip), "{:?}", engine);
+        assert!(run.memory == first.memory, "{:?}", engine);
+    }
+    runs.into_iter().next().unwrap()
+}
+
+fn run(source: &str) -> Run {
+    run_with(&asm(source), &[], "")
+}
+
+fn r(run: &Run, index: usize) -> u16 {
+    run.registers[index]
+}
+
+#[test]
+fn example_program() {
+    // "9,32768,32769,4,19,32768" with r1 = 61, then halt
+    let run = run_with(&[9, 32768, 32769, 4, 19, 32768, 0], &[(1, 61)], "");
+    assert_eq!(run.outcome, Outcome::Halted);
+    assert_eq!(r(&run, 0), 65);
+    assert_eq!(run.output, "A");
+}
+
+#[test]
+fn example_program_runs_off_the_end() {
+    let run = run_with(&[9, 32768, 32769, 4, 19, 32768], &[(1, 61)], "");
+    assert_eq!(run.outcome, Outcome::OutOfMemory);
+    assert_eq!(run.output, "A");
+    assert_eq!(run.ip, 6);
+}
+
+#[test]
+fn halt() {
+    let run = run("halt\nout 65");
+    assert_eq!(run.outcome, Outcome::Halted);
+    assert_eq!(run.output, "");
+    assert_eq!(run.ip, 0);
+}
+
+#[test]
+fn set() {
+    let run = run("set r0 32767\nset r1 r0\nset r7 0\nhalt");
+    assert_eq!(run.registers, vec![32767, 32767, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn set_to_a_literal_is_an_error() {
+    let run = run("set 3 4\nhalt");
+    assert_eq!(run.outcome, Outcome::Fault(Fault::NotARegister(3)));
+}
+
+#[test]
+fn push_and_pop() {
+    let run = run("set r0 7\npush r0\npush 9\npop r1\npop r2\npush 3\nhalt");
+    assert_eq!((r(&run, 1), r(&run, 2)), (9, 7));
+    assert_eq!(run.stack, vec![3]);
+}
+
+#[test]
+fn pop_on_empty_stack_is_an_error() {
+    let run = run("push 1\npop r0\npop r1\nhalt");
+    assert_eq!(run.outcome, Outcome::Fault(Fault::EmptyStack));
+    assert_eq!(run.ip, 4);
+    assert_eq!(r(&run, 0), 1);
+}
+
+#[test]
+fn eq() {
+    let run = run_with(&asm("eq r0 r7 5\neq r1 r7 6\neq r2 3 3\nhalt"), &[(7, 5)], "");
+    assert_eq!((r(&run, 0), r(&run, 1), r(&run, 2)), (1, 0, 1));
+}
+
+#[test]
+fn gt() {
+    let run = run("gt r0 5 4\ngt r1 4 5\ngt r2 4 4\ngt r3 32767 0\nhalt");
+    assert_eq!(&run.registers[..4], &[1, 0, 0, 1]);
+}
+
+#[test]
+fn jmp() {
+    // 0: jmp 4, 2: out 66, 4: out 65, 6: halt
+    let run = run("jmp 4\nout 66\nout 65\nhalt");
+    assert_eq!(run.output, "A");
+}
+
+#[test]
+fn jmp_to_a_register() {
+    let run = run_with(&asm("jmp r0\nout 66\nout 65\nhalt"), &[(0, 4)], "");
+    assert_eq!(run.output, "A");
+}
+
+#[test]
+fn jt() {
+    // 0: jt r0 5, 3: out 66, 5: out 65, 7: halt
+    let taken = run_with(&asm("jt r0 5\nout 66\nout 65\nhalt"), &[(0, 32767)], "");
+    assert_eq!(taken.output, "A");
+    let not_taken = run("jt r0 5\nout 66\nout 65\nhalt");
+    assert_eq!(not_taken.output, "BA");
+}
+
+#[test]
+fn jf() {
+    let taken = run("jf r0 5\nout 66\nout 65\nhalt");
+    assert_eq!(taken.output, "A");
+    let not_taken = run_with(&asm("jf r0 5\nout 66\nout 65\nhalt"), &[(0, 1)], "");
+    assert_eq!(not_taken.output, "BA");
+}
+
+#[test]
+fn add() {
+    let run = run("add r0 1 2\nadd r1 r0 r0\nhalt");
+    assert_eq!((r(&run, 0), r(&run, 1)), (3, 6));
+}
+
+#[test]
+fn add_wraps_around() {
+    let run = run("add r0 32758 15\nadd r1 32767 32767\nadd r2 32767 1\nhalt");
+    assert_eq!(&run.registers[..3], &[5, 32766, 0]);
+}
+
+#[test]
+fn mult() {
+    let run = run("mult r0 6 7\nhalt");
+    assert_eq!(r(&run, 0), 42);
+}
+
+#[test]
+fn mult_wraps_around() {
+    // 32767 * 32767 = 2^30 - 2^16 + 1, and 200 * 200 = 40000
+    let run = run("mult r0 32767 32767\nmult r1 200 200\nmult r2 16384 2\nhalt");
+    assert_eq!(&run.registers[..3], &[1, 40000 - 32768, 0]);
+}
+
+#[test]
+fn modulo() {
+    let run = run("mod r0 17 5\nmod r1 4 5\nmod r2 32767 32767\nhalt");
+    assert_eq!(&run.registers[..3], &[2, 4, 0]);
+}
+
+#[test]
+fn mod_by_zero_is_an_error() {
+    let run = run("mod r0 17 r1\nhalt");
+    assert_eq!(run.outcome, Outcome::Fault(Fault::DivisionByZero));
+}
+
+#[test]
+fn and_or() {
+    let run = run("and r0 12 10\nor r1 12 10\nand r2 32767 21845\nor r3 16384 1\nhalt");
+    assert_eq!(&run.registers[..4], &[8, 14, 21845, 16385]);
+}
+
+#[test]
+fn not_is_15_bits() {
+    let run = run("not r0 0\nnot r1 32767\nnot r2 21845\nhalt");
+    assert_eq!(&run.registers[..3], &[32767, 0, 10922]);
+}
+
+#[test]
+fn rmem() {
+    // 0: rmem r0 7, 3: rmem r1 r2, 6: halt, 7: 1234
+    let mut program = asm("rmem r0 7\nrmem r1 r2\nhalt");
+    program.push(1234);
+    let run = run_with(&program, &[(2, 7)], "");
+    assert_eq!((r(&run, 0), r(&run, 1)), (1234, 1234));
+}
+
+#[test]
+fn wmem() {
+    // 0: wmem 10 r0, 3: wmem r1 77, 6: halt
+    let mut program = asm("wmem 10 r0\nwmem r1 77\nhalt");
+    program.resize(16, 0);
+    let run = run_with(&program, &[(0, 99), (1, 12)], "");
+    assert_eq!((run.memory[10], run.memory[12]), (99, 77));
+}
+
+#[test]
+fn wmem_then_rmem_through_registers() {
+    let mut program = asm("set r0 20\nwmem r0 r0\nrmem r1 r0\nhalt");
+    program.resize(21, 0);
+    let run = run_with(&program, &[], "");
+    assert_eq!(r(&run, 1), 20);
+}
+
+#[test]
+fn wmem_can_rewrite_the_next_instruction() {
+    // 0: wmem 4 66, 3: out 65, 5: halt; the write turns `out 65` into `out 66`
+    let run = run("wmem 4 66\nout 65\nhalt");
+    assert_eq!(run.output, "B");
+}
+
+#[test]
+fn call_and_ret() {
+    // 0: call 5, 2: out 66, 4: halt, 5: out 65, 7: ret
+    let run = run("call 5\nout 66\nhalt\nout 65\nret");
+    assert_eq!(run.output, "AB");
+    assert!(run.stack.is_empty());
+}
+
+#[test]
+fn call_pushes_the_next_address() {
+    // 0: call r0, 2: halt, 3: halt
+    let run = run_with(&asm("call r0\nhalt\nhalt"), &[(0, 3)], "");
+    assert_eq!(run.ip, 3);
+    assert_eq!(run.stack, vec![2]);
+}
+
+#[test]
+fn ret_jumps_to_the_top_of_the_stack() {
+    // 0: push 5, 2: ret, 3: out 66, 5: out 65, 7: halt
+    let run = run("push 5\nret\nout 66\nout 65\nhalt");
+    assert_eq!(run.output, "A");
+}
+
+#[test]
+fn ret_on_empty_stack_halts() {
+    let run = run("ret\nout 65");
+    assert_eq!(run.outcome, Outcome::Halted);
+    assert_eq!(run.output, "");
+}
+
+#[test]
+fn out() {
+    let run = run_with(&asm("out 72\nout r0\nout 10\nhalt"), &[(0, 105)], "");
+    assert_eq!(run.output, "Hi\n");
+}
+
+#[test]
+fn in_reads_a_character() {
+    let run = run_with(&asm("in r0\nin r1\nin r2\nhalt"), &[], "ok\n");
+    assert_eq!(&run.registers[..3], &[111, 107, 10]);
+    assert_eq!(run.outcome, Outcome::Halted);
+}
+
+#[test]
+fn in_waits_for_input() {
+    let run = run_with(&asm("in r0\nin r1\nhalt"), &[], "a");
+    assert_eq!(run.outcome, Outcome::NeedInput);
+    assert_eq!(run.ip, 2);
+    assert_eq!(r(&run, 0), 97);
+}
+
+#[test]
+fn noop() {
+    let run = run("noop\nnoop\nout 65\nhalt");
+    assert_eq!(run.output, "A");
+    assert_eq!(run.ip, 4);
+}
+
+#[test]
+fn invalid_operands_are_errors() {
+    let run = run_with(&[9, 32768, 32776, 1, 0], &[], "");
+    assert_eq!(run.outcome, Outcome::Fault(Fault::InvalidOperand(32776)));
+    assert_eq!(run.ip, 0);
+}
+
+#[test]
+fn unknown_opcodes_are_errors() {
+    // 0: out 65, 2: 22, 3: out 66
+    let run = run_with(&[19, 65, 22, 19, 66, 0], &[], "");
+    assert_eq!(run.outcome, Outcome::Fault(Fault::UnknownOpcode(22)));
+    assert_eq!(run.output, "A");
+    assert_eq!(run.ip, 2);
+    let run = run_with(&[32768], &[], "");
+    assert_eq!(run.outcome, Outcome::Fault(Fault::UnknownOpcode(32768)));
+}
@@ -0,0 +1,42 @@
+use synacor_challenge::read_input_u16;
+use synacor_challenge::strings::{self, Entry};
+
+fn all() -> Vec<Entry> {
+    strings::strings(&read_input_u16("input/challenge.bin")).unwrap()
+}
+
+fn containing<'a>(strings: &'a [Entry], text: &str) -> Vec<&'a Entry> {
+    strings.iter().filter(|entry| entry.text.contains(text)).collect()
+}
+
+#[test]
+fn finds_the_string_routine() {
+    assert_eq!(strings::string_routine(&read_input_u16("input/challenge.bin")), Some(1458));
+}
+
+#[test]
+fn room_texts_are_decrypted_at_start() {
+    let strings = all();
+    let foothills = containing(&strings, "Foothills");
+    assert_eq!(foothills.len(), 2);
+    assert_eq!(foothills[0].reference, None);
+    assert!(!containing(&strings, "Keep out!  Definitely no treasure within!").is_empty());
+    assert!(strings.windows(2).all(|w| w[0].address < w[1].address));
+}
+
+#[test]
+fn obfuscated_messages_are_decoded() {
+    let strings = all();
+    let no_item = containing(&strings, "You see no such item.");
+    assert_eq!(no_item.len(), 1);
+    assert_eq!(no_item[0].reference, Some(3022));
+    assert!(!containing(&strings, "you hear a click from the north door").is_empty());
+    assert!(strings.iter().all(|entry| entry.text.chars().all(|c| c == '\n' || (' '..='~').contains(&c))));
+}
+
+#[test]
+fn plain_strings_are_length_prefixed() {
+    let memory = [2, 104, 105, 3, 97, 98, 99, 9, 5, 120];
+    let found: Vec<(usize, String)> = strings::plain_strings(&memory).into_iter().map(|e| (e.address, e.text)).collect();
+    assert_eq!(found, vec![(3, "abc".to_string())]);
+}
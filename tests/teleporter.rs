@@ -0,0 +1,70 @@
+use synacor_challenge::analysis;
+use synacor_challenge::disasm;
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::TELEPORTER_R7;
+use synacor_challenge::teleporter::{self, Ackermann, Bypass};
+
+#[test]
+fn native_routine_matches_the_guest() {
+    let program = read_input_u16("input/challenge.bin");
+    let site = teleporter::find_call_site(&program).unwrap();
+    assert_eq!(site.function, 6027);
+    assert_eq!((site.r0, site.r1, site.target), (4, 1, 6));
+    assert_eq!(teleporter::check_native(&program, site.function), vec![]);
+}
+
+#[test]
+fn native_ackermann_matches_the_tables() {
+    let mut af = Ackermann::new();
+    for &r7 in [0, 1, 2, 3, 100, 12345, TELEPORTER_R7, 32767].iter() {
+        for m in 0..5 {
+            // af(4, n) applies af(3, _) n + 1 times, each taking up to 32768 steps
+            let ns: &[u16] = if m < 4 { &[0, 1, 2, 7, 32767] } else { &[0, 1, 2, 7] };
+            for &n in ns.iter() {
+                assert_eq!(teleporter::ackermann_native(m, n, r7), af.eval(m, n, r7), "af({}, {}) with r7 = {}", m, n, r7);
+            }
+        }
+    }
+    assert_eq!(teleporter::ackermann_native(4, 1, TELEPORTER_R7), 6);
+}
+
+#[test]
+fn finds_the_teleporter_r7() {
+    let program = read_input_u16("input/challenge.bin");
+    let site = teleporter::find_call_site(&program).unwrap();
+    assert_eq!((site.address, site.function, site.check), (5489, 6027, 5491));
+    assert_eq!((site.r0, site.r1, site.target), (4, 1, 6));
+    assert_eq!(teleporter::find_r7(&site), vec![TELEPORTER_R7]);
+}
+
+#[test]
+fn bypass_patches_the_confirmation_routine() {
+    let program = read_input_u16("input/challenge.bin");
+    assert_eq!(teleporter::confirmation_routines(&program), vec![6027]);
+    let site = teleporter::find_call_site(&program).unwrap();
+    let bypass = Bypass::new(&site);
+    assert_eq!(bypass.address, site.function);
+    assert_eq!(bypass.to_string(), "patch 6027: 1,32768,6,18");
+
+    // a build of its own, the routine moved to 14
+    let program = [1, 32768, 4, 1, 32769, 1, 17, 14, 4, 32769, 32768, 6, 0, 21,
+                   9, 32768, 32775, 1, 17, 14, 18];
+    assert_eq!(teleporter::confirmation_routines(&program), vec![14]);
+    let site = teleporter::find_call_site(&program).unwrap();
+    assert_eq!((site.address, site.function, site.check, site.target), (6, 14, 8, 6));
+    assert_eq!(Bypass::new(&site).to_string(), "patch 14: 1,32768,6,18");
+}
+
+#[test]
+fn register_usage_finds_every_read_of_r7() {
+    let program = read_input_u16("input/challenge.bin");
+    let usage = analysis::register_usage(&program);
+    let reads: Vec<usize> = usage[7].reads.iter().map(|i| i.address).collect();
+    // the handler of the teleporter, at 5451, is only reached through the item table
+    assert_eq!(reads, [521, 5451, 5522, 6042]);
+    assert!(usage[7].writes.is_empty());
+
+    // the sweep stays aligned: no instruction starts inside another
+    let code = disasm::all_code(&program);
+    assert!(code.values().zip(code.values().skip(1)).all(|(a, b)| a.next() <= b.address));
+}
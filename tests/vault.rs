@@ -0,0 +1,86 @@
+use std::fs;
+
+use synacor_challenge::coins;
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::{SynacorVm, TELEPORTER_R7};
+use synacor_challenge::teleporter::{self, Bypass};
+use synacor_challenge::vault::{self, Cell, Direction, Grid, Op};
+
+const TO_MONUMENT: &str = "doorway\nnorth\nnorth\nbridge\ncontinue\ndown\neast\ntake empty lantern\nwest\nwest\n\
+    passage\nladder\nwest\nsouth\nnorth\ntake can\nuse can\nuse lantern\nwest\nladder\ndarkness\ncontinue\n\
+    west\nwest\nwest\nwest\nnorth\ntake red coin\nnorth\neast\ntake concave coin\ndown\ntake corroded coin\n\
+    up\nwest\nwest\ntake blue coin\nup\ntake shiny coin\ndown\neast\n";
+
+// The game waiting in the antechamber, the orb still on its pedestal.
+fn antechamber() -> SynacorVm {
+    let program = read_input_u16("input/challenge.bin");
+    let bypass = Bypass::new(&teleporter::find_call_site(&program).unwrap());
+    let mut vm = SynacorVm::new(program);
+    vm.patch(bypass.address, &bypass.words);
+    vm.feed(TO_MONUMENT);
+    vm.run_until_input();
+    vm.feed(&coins::coin_commands(&vm).unwrap());
+    vm.feed("north\ntake teleporter\nuse teleporter\ntake business card\n");
+    vm.run_until_input();
+    vm.set_register(7, TELEPORTER_R7);
+    vm.feed("use teleporter\n");
+    vm.feed(&"north\n".repeat(9));
+    vm.run_until_input();
+    vm.take_output();
+    vm
+}
+
+#[test]
+fn json_grid_is_the_game_grid() {
+    let grid = Grid::from_json(&fs::read_to_string("input/vault.json").unwrap()).unwrap();
+    assert_eq!((grid.width, grid.height), (4, 4));
+    let vm = antechamber();
+    assert_eq!(vault::probe(&vm).unwrap(), grid);
+    let route = grid.solve().unwrap();
+    assert_eq!(route.len(), 12);
+    assert!(vault::verify(&vm, &route));
+    assert_eq!(vault::grid_commands(&grid).unwrap(), vault::commands(&vm).unwrap());
+}
+
+#[test]
+fn operators_come_from_the_grid() {
+    let grid = Grid::from_json(r#"{"operators": {"%": "mod", "&": "add"}, "rows": [["10", "%", "4"], [null, "&", "1"]],
+                                   "start": [0, 0], "target": [0, 2], "target_value": 2}"#).unwrap();
+    assert_eq!(grid.cells[1], Some(Cell::Op(Op::Mod)));
+    assert_eq!(grid.cells[3], None);
+    assert_eq!(grid.start_value, 10);
+    assert_eq!(grid.solve(), Some(vec![Direction::East, Direction::East]));
+    assert_eq!(vault::grid_commands(&grid).unwrap(), "take orb\neast\neast\nvault\n");
+
+    // the game's operators when none are given
+    let grid = Grid::from_json(r#"{"rows": [["3", "*", "4"]], "start": [0, 0], "target": [0, 2], "target_value": 12}"#);
+    assert_eq!(grid.unwrap().cells[1], Some(Cell::Op(Op::Mul)));
+    let grid = Grid::from_json(r#"{"operators": {"%": "mod"}, "rows": [["3", "*", "4"]], "start": [0, 0],
+                                   "target": [0, 2], "target_value": 12}"#);
+    assert_eq!(grid.unwrap_err(), "unknown cell *");
+    let grid = Grid::from_json(r#"{"operators": {"%": "pow"}, "rows": [["3"]], "start": [0, 0], "target": [0, 0],
+                                   "target_value": 3}"#);
+    assert_eq!(grid.unwrap_err(), "unknown operator \"pow\"");
+}
+
+#[test]
+fn malformed_grids() {
+    for (json, error) in [
+        ("{}", "missing rows"),
+        (r#"{"rows": [[]]}"#, "empty grid"),
+        (r#"{"rows": [["1", "+"], ["2"]]}"#, "rows have different lengths"),
+        (r#"{"rows": [["1", "+"]], "start": [0, 2], "target": [0, 0]}"#, "bad start"),
+        (r#"{"rows": [["1", "+"]], "start": [0, 0], "target": [0, 0]}"#, "missing target_value"),
+        (r#"{"rows": [["+", "1"]], "start": [0, 0], "target": [0, 1], "target_value": 1}"#, "missing start_value")
+    ] {
+        assert_eq!(Grid::from_json(json).unwrap_err(), error, "{}", json);
+    }
+}
+
+#[test]
+fn unreachable_targets() {
+    let grid = Grid::from_json(r#"{"rows": [["1", "+", "1"]], "start": [0, 0], "target": [0, 2],
+                                   "target_value": 3}"#).unwrap();
+    assert_eq!(grid.solve(), None);
+    assert_eq!(vault::grid_commands(&grid).unwrap_err(), "no route through the grid");
+}
@@ -0,0 +1,55 @@
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use synacor_challenge::read_input_u16;
+use synacor_challenge::synacor_vm::{Budget, Engine, Outcome, SynacorVm, COMMAND_BUDGET, TELEPORTER_R7};
+use synacor_challenge::teleporter::{self, Bypass};
+use synacor_challenge::walkthrough;
+
+// Plays the whole game headless, with the confirmation routine bypassed, and
+// no input past the walkthrough.
+fn play() -> (Outcome, String) {
+    let program = read_input_u16("input/challenge.bin");
+    let site = teleporter::find_call_site(&program).expect("no call to the confirmation routine");
+    let bypass = Bypass::new(&site);
+    let mut vm = SynacorVm::new(program);
+    vm.patch(bypass.address, &bypass.words);
+    vm.set_engine(Engine::Blocks);
+
+    let (prepared, second_prepared) = walkthrough::scripts(&vm, TELEPORTER_R7).unwrap();
+    let budget = Budget {
+        instructions: Some(COMMAND_BUDGET),
+        deadline: Some(Instant::now() + Duration::from_secs(60)),
+        cancel: None
+    };
+    let mut output = Vec::new();
+    let outcome = vm.run_io(&prepared, &second_prepared, &budget, Cursor::new(""), &mut output).unwrap();
+    (outcome, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn walkthrough_finds_every_code() {
+    let (outcome, output) = play();
+    // waiting in the vault for a command that never comes
+    assert_eq!(outcome, Outcome::NeedInput);
+
+    let rooms = ["Foothills", "Dark cave", "Rope bridge", "Moss cavern", "Twisty passages", "Ruins",
+                 "Synacor Headquarters", "Beach", "Tropical Island", "Tropical Cave", "Vault Antechamber",
+                 "Vault Lock", "Vault Door", "Vault"];
+    let mut rest = output.as_str();
+    for room in rooms.iter() {
+        let header = format!("== {} ==", room);
+        let found = rest.find(&header).unwrap_or_else(|| panic!("never reached {}", room));
+        rest = &rest[found + header.len()..];
+    }
+
+    let codes: Vec<&str> = output.lines().filter(|line| line.starts_with("code: ")).collect();
+    assert_eq!(codes, vec![
+        "code: SaFPTyYYPxtc - mirrored: ctxPYYyTPFaS",
+        "code: xHUCoNHlSgmn - mirrored: nmgSlHNoCUHx",
+        "code: frOgQzZlguet - mirrored: teuglZzQgOrf",
+        "code: buMeVvBwqgHJ - mirrored: JHgpwBvVeMud",
+        "code: uQWfNPkCwlXV - mirrored: VXlwCkPNfWQu",
+        "code: qAddloUWuIAd - seen in the mirror, submit bAIuWUolbbAp"
+    ]);
+}